@@ -0,0 +1,96 @@
+use crate::vec3::Vec3;
+use crate::ray::Ray;
+
+// Axis aligned bounding box
+#[derive(Debug, Clone)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        Aabb {
+            min,
+            max
+        }
+    }
+
+    // Contains nothing, the neutral element of union
+    pub fn empty() -> Aabb {
+        Aabb::new(
+            Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY)
+        )
+    }
+
+    // Used by primitives that extend forever, like planes
+    pub fn infinite() -> Aabb {
+        Aabb::new(
+            Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY)
+        )
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min.x.is_finite() && self.min.y.is_finite() && self.min.z.is_finite()
+            && self.max.x.is_finite() && self.max.y.is_finite() && self.max.z.is_finite()
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Vec3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            Vec3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z))
+        )
+    }
+
+    pub fn grow(&self, point: &Vec3) -> Aabb {
+        self.union(&Aabb::new(point.clone(), point.clone()))
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (&self.min + &self.max) * 0.5
+    }
+
+    pub fn extent(&self) -> Vec3 {
+        &self.max - &self.min
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let e = self.extent();
+        if e.x < 0.0 || e.y < 0.0 || e.z < 0.0 {
+            return 0.0
+        }
+        2.0 * (e.x * e.y + e.y * e.z + e.z * e.x)
+    }
+
+    pub fn largest_axis(&self) -> usize {
+        let e = self.extent();
+        if e.x >= e.y && e.x >= e.z {
+            0
+        } else if e.y >= e.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    // Slab test, `inverse_direction` is 1 / ray.direction computed once per ray.
+    // Returns the distance at which the ray enters the box if that happens
+    // before `t_max`.
+    pub fn hit(&self, ray: &Ray, inverse_direction: &Vec3, t_max: f32) -> Option<f32> {
+        let mut t_enter = 0.0f32;
+        let mut t_exit = t_max;
+        for axis in 0..3 {
+            let t1 = (self.min[axis] - ray.origin[axis]) * inverse_direction[axis];
+            let t2 = (self.max[axis] - ray.origin[axis]) * inverse_direction[axis];
+            t_enter = t_enter.max(t1.min(t2));
+            t_exit = t_exit.min(t1.max(t2));
+        }
+        if t_enter <= t_exit {
+            Some(t_enter)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::vec3::Vec3;
+
+// Every pixel first takes `min_samples` samples, then keeps taking batches of
+// `batch_size` until its relative noise drops under `threshold` or it reaches
+// `max_samples`.
+#[derive(Debug, Clone)]
+pub struct AdaptiveSampling {
+    pub min_samples: usize,
+    pub max_samples: usize,
+    pub batch_size: usize,
+    pub threshold: f32
+}
+
+impl AdaptiveSampling {
+    pub fn new(min_samples: usize, max_samples: usize, threshold: f32) -> AdaptiveSampling {
+        let min_samples = min_samples.max(2);
+        AdaptiveSampling {
+            min_samples,
+            max_samples: max_samples.max(min_samples),
+            batch_size: min_samples,
+            threshold
+        }
+    }
+
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1)
+    }
+
+    pub(crate) fn is_converged(&self, stats: &PixelStats) -> bool {
+        stats.count >= self.max_samples
+            || (stats.count >= self.min_samples && stats.relative_error() < self.threshold)
+    }
+}
+
+// Running mean and variance of the luminance of a pixel's samples (Welford)
+#[derive(Debug, Clone)]
+pub(crate) struct PixelStats {
+    pub count: usize,
+    pub sum: Vec3,
+    mean: f32,
+    m2: f32
+}
+
+impl PixelStats {
+    pub fn new() -> PixelStats {
+        PixelStats {
+            count: 0,
+            sum: Vec3::new(0.0, 0.0, 0.0),
+            mean: 0.0,
+            m2: 0.0
+        }
+    }
+
+    pub fn push(&mut self, color: &Vec3) {
+        let luminance = 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+        self.count += 1;
+        self.sum = &self.sum + color;
+
+        let delta = luminance - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (luminance - self.mean);
+    }
+
+    pub fn average(&self) -> Vec3 {
+        if self.count == 0 {
+            return self.sum.clone()
+        }
+        &self.sum / self.count as f32
+    }
+
+    // Standard error of the mean luminance relative to the mean itself. The
+    // small bias keeps nearly black pixels from chasing noise nobody can see.
+    pub fn relative_error(&self) -> f32 {
+        if self.count < 2 {
+            return f32::INFINITY
+        }
+        let variance = self.m2 / (self.count - 1) as f32;
+        (variance / self.count as f32).sqrt() / (self.mean + 0.01)
+    }
+}
+
+impl Default for PixelStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,308 @@
+use std::f32::consts::PI;
+
+use crate::{
+    vec3::Vec3,
+    hdr::HdrImage
+};
+
+// Light coming from infinitely far away, seen by the rays leaving the scene
+pub trait Background: Sync + Send {
+    // Radiance arriving from `direction`, a normalized direction pointing away
+    // from the scene
+    fn radiance(&self, direction: &Vec3) -> Vec3;
+
+    // Direction picked from `u` in [0, 1)² and its pdf over the sphere of
+    // directions. Backgrounds without a better strategy sample the sphere
+    // uniformly.
+    fn sample(&self, u: (f32, f32)) -> (Vec3, f32) {
+        (uniform_sphere(u), 1.0 / (4.0 * PI))
+    }
+
+    // Pdf of `sample` returning `direction`
+    fn pdf(&self, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+fn uniform_sphere(u: (f32, f32)) -> Vec3 {
+    let cos_theta = 1.0 - 2.0 * u.0;
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin())
+}
+
+// The same color in every direction
+#[derive(Debug, Clone)]
+pub struct ConstantBackground {
+    pub color: Vec3
+}
+
+impl ConstantBackground {
+    pub fn new(color: Vec3) -> ConstantBackground {
+        ConstantBackground {
+            color
+        }
+    }
+}
+
+impl Background for ConstantBackground {
+    fn radiance(&self, _direction: &Vec3) -> Vec3 {
+        self.color.clone()
+    }
+}
+
+// Blends from `bottom` straight down to `top` straight up, along `up`
+#[derive(Debug, Clone)]
+pub struct GradientBackground {
+    pub bottom: Vec3,
+    pub top: Vec3,
+    pub up: Vec3
+}
+
+impl GradientBackground {
+    pub fn new(bottom: Vec3, top: Vec3) -> GradientBackground {
+        GradientBackground {
+            bottom,
+            top,
+            up: Vec3::new(0.0, 1.0, 0.0)
+        }
+    }
+
+    pub fn with_up(mut self, up: Vec3) -> GradientBackground {
+        self.up = up.normalize();
+        self
+    }
+}
+
+impl Background for GradientBackground {
+    fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let t = 0.5 * (1.0 + Vec3::dot(direction, &self.up));
+        &self.bottom + (&self.top - &self.bottom) * t
+    }
+}
+
+// Equirectangular (latitude-longitude) image around the scene, +y up. The
+// center of the image is toward -z, in front of the default camera.
+// Directions are importance sampled in proportion to the brightness of the
+// pixels, so small bright features like the sun are found by the shadow rays.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    image: HdrImage,
+    // Running sums of the pixel weights of every row
+    row_cdfs: Vec<Vec<f32>>,
+    // Running sum of the row totals
+    marginal_cdf: Vec<f32>
+}
+
+impl EnvironmentMap {
+    pub fn new(image: HdrImage) -> EnvironmentMap {
+        let (width, height) = (image.width, image.height);
+        let mut row_cdfs = Vec::with_capacity(height);
+        let mut marginal_cdf = Vec::with_capacity(height);
+        let mut total = 0.0;
+        for y in 0..height {
+            // Rows near the poles cover a smaller solid angle
+            let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+            let mut sum = 0.0;
+            let row = image.pixels[y * width..(y + 1) * width].iter().map(|pixel| {
+                sum += luminance(pixel) * sin_theta;
+                sum
+            }).collect();
+            row_cdfs.push(row);
+            total += sum;
+            marginal_cdf.push(total);
+        }
+        EnvironmentMap {
+            image,
+            row_cdfs,
+            marginal_cdf
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.image.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.image.height
+    }
+
+    fn total(&self) -> f32 {
+        self.marginal_cdf.last().copied().unwrap_or(0.0)
+    }
+
+    // Pixel containing a direction, and the direction as (u, v) in [0, 1]²
+    fn pixel(&self, direction: &Vec3) -> (usize, usize) {
+        let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * PI);
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+        let x = ((u * self.width() as f32) as usize).min(self.width() - 1);
+        let y = ((v * self.height() as f32) as usize).min(self.height() - 1);
+        (x, y)
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn radiance(&self, direction: &Vec3) -> Vec3 {
+        let (x, y) = self.pixel(direction);
+        self.image.pixels[y * self.width() + x].clone()
+    }
+
+    fn sample(&self, u: (f32, f32)) -> (Vec3, f32) {
+        let total = self.total();
+        if total <= 0.0 {
+            return (uniform_sphere(u), 1.0 / (4.0 * PI))
+        }
+        let (y, v_offset) = pick(&self.marginal_cdf, u.1 * total);
+        let row = &self.row_cdfs[y];
+        let (x, u_offset) = pick(row, u.0 * row[row.len() - 1]);
+
+        let u = (x as f32 + u_offset) / self.width() as f32;
+        let v = (y as f32 + v_offset) / self.height() as f32;
+        let (phi, theta) = ((u - 0.5) * 2.0 * PI, v * PI);
+        let direction = Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos());
+        (direction.clone(), self.pdf(&direction))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f32 {
+        let total = self.total();
+        if total <= 0.0 {
+            return 1.0 / (4.0 * PI)
+        }
+        let (x, y) = self.pixel(direction);
+        let row = &self.row_cdfs[y];
+        let weight = row[x] - if x > 0 { row[x - 1] } else { 0.0 };
+        // The solid angle shrinks across the pixel toward the poles
+        let sin_theta = (1.0 - direction.y * direction.y).max(0.0).sqrt();
+        if sin_theta <= 0.0 {
+            return 0.0
+        }
+        // From the probability of the pixel to a density over its solid angle
+        let pixel_solid_angle = 2.0 * PI * PI * sin_theta / (self.width() * self.height()) as f32;
+        weight / total / pixel_solid_angle
+    }
+}
+
+fn luminance(color: &Vec3) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+// Index of the entry of a running sum containing `target`, and where
+// `target` falls inside that entry in [0, 1)
+fn pick(cdf: &[f32], target: f32) -> (usize, f32) {
+    let index = cdf.partition_point(|&sum| sum <= target).min(cdf.len() - 1);
+    let start = if index > 0 { cdf[index - 1] } else { 0.0 };
+    let width = cdf[index] - start;
+    let offset = if width > 0.0 { ((target - start) / width).clamp(0.0, 0.9999) } else { 0.5 };
+    (index, offset)
+}
+
+// The background of a scene, turned around the vertical axis by `rotation`
+// radians and scaled by `intensity`
+pub struct Environment {
+    background: Box<dyn Background>,
+    rotation: f32,
+    intensity: f32
+}
+
+impl Environment {
+    pub fn new<T: Background + 'static>(background: T) -> Environment {
+        Environment {
+            background: Box::new(background),
+            rotation: 0.0,
+            intensity: 1.0
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Environment {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Environment {
+        self.intensity = intensity;
+        self
+    }
+
+    // Turns a direction of the scene into the frame of the background, or
+    // back with a negative angle
+    fn rotate(&self, direction: &Vec3, angle: f32) -> Vec3 {
+        let (sin, cos) = angle.sin_cos();
+        Vec3::new(cos * direction.x + sin * direction.z, direction.y, cos * direction.z - sin * direction.x)
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> Vec3 {
+        self.background.radiance(&self.rotate(direction, -self.rotation)) * self.intensity
+    }
+
+    // Direction toward the background, its radiance and its pdf
+    pub fn sample(&self, u: (f32, f32)) -> (Vec3, Vec3, f32) {
+        let (local, pdf) = self.background.sample(u);
+        let radiance = self.background.radiance(&local) * self.intensity;
+        (self.rotate(&local, self.rotation), radiance, pdf)
+    }
+
+    pub fn pdf(&self, direction: &Vec3) -> f32 {
+        self.background.pdf(&self.rotate(direction, -self.rotation))
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::new(ConstantBackground::new(Vec3::new(0.2, 0.7, 0.9)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment_maps_sample_the_bright_pixels() {
+        // A dark image with a single bright pixel
+        let (width, height) = (16, 8);
+        let mut pixels = vec![Vec3::new(0.01, 0.01, 0.01); width * height];
+        pixels[2 * width + 5] = Vec3::new(100.0, 100.0, 100.0);
+        let map = EnvironmentMap::new(HdrImage { width, height, pixels });
+
+        let bright = (0..16).filter(|i| {
+            let (direction, pdf) = map.sample((*i as f32 / 16.0, 0.37));
+            assert!((pdf - map.pdf(&direction)).abs() < 1e-3 * pdf, "{} {}", pdf, map.pdf(&direction));
+            map.radiance(&direction).x > 1.0
+        }).count();
+        assert!(bright >= 15, "{}", bright);
+    }
+
+    #[test]
+    fn environment_map_pdf_integrates_to_one() {
+        // Brightest next to the pole, where the solid angle changes the most
+        // across a pixel
+        let (width, height) = (16, 8);
+        let mut pixels = (0..width * height).map(|i| Vec3::new(1.0, 1.0, 1.0) * (i % 7) as f32).collect::<Vec<_>>();
+        pixels[3] = Vec3::new(1000.0, 1000.0, 1000.0);
+        let map = EnvironmentMap::new(HdrImage { width, height, pixels });
+
+        // Sum over a grid of (theta, phi) cells, each weighted by its exact solid angle
+        let (rows, columns) = (height * 16, width * 16);
+        let integral: f32 = (0..rows).map(|j| {
+            let (top, bottom) = (PI * j as f32 / rows as f32, PI * (j + 1) as f32 / rows as f32);
+            let theta = (top + bottom) / 2.0;
+            let solid_angle = (top.cos() - bottom.cos()) * 2.0 * PI / columns as f32;
+            (0..columns).map(|i| {
+                let phi = 2.0 * PI * (i as f32 + 0.5) / columns as f32;
+                let direction = Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos());
+                map.pdf(&direction) * solid_angle
+            }).sum::<f32>()
+        }).sum();
+        assert!((integral - 1.0).abs() < 1e-3, "{}", integral);
+    }
+
+    #[test]
+    fn rotation_turns_the_background_around_the_vertical() {
+        let environment = Environment::new(GradientBackground::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0)).with_up(Vec3::new(1.0, 0.0, 0.0)))
+            .with_rotation(PI / 2.0)
+            .with_intensity(2.0);
+        // The gradient now rises toward -z
+        let radiance = environment.radiance(&Vec3::new(0.0, 0.0, -1.0));
+        assert!((radiance.x - 2.0).abs() < 1e-5, "{:?}", radiance);
+    }
+}
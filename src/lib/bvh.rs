@@ -0,0 +1,289 @@
+use crate::{
+    vec3::Vec3,
+    ray::Ray,
+    aabb::Aabb
+};
+
+const BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+// Keeps the traversal stack within a fixed size array
+const MAX_DEPTH: usize = 60;
+
+#[derive(Debug, Clone)]
+struct Node {
+    bounds: Aabb,
+    // For a leaf, the range of `indices` holding its primitives. An interior
+    // node has a count of 0, its left child is stored right after it and
+    // `first` is the index of its right child.
+    first: usize,
+    count: usize,
+    axis: usize
+}
+
+// Bounding volume hierarchy over primitives identified by their index, built
+// with the surface area heuristic. Primitives without a finite bounding box
+// (planes) cannot be placed in the tree and are tested for every ray.
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+    unbounded: Vec<usize>
+}
+
+impl Bvh {
+    pub fn build(bounds: &[Aabb]) -> Bvh {
+        let mut indices = vec![];
+        let mut unbounded = vec![];
+        for (index, bound) in bounds.iter().enumerate() {
+            if bound.is_finite() {
+                indices.push(index)
+            } else {
+                unbounded.push(index)
+            }
+        }
+        let centroids: Vec<Vec3> = bounds.iter().map(|bound| bound.centroid()).collect();
+
+        let mut bvh = Bvh {
+            nodes: vec![],
+            indices,
+            unbounded
+        };
+        if !bvh.indices.is_empty() {
+            bvh.build_node(bounds, &centroids, 0, bvh.indices.len(), 0);
+        }
+        bvh
+    }
+
+    fn build_node(&mut self, bounds: &[Aabb], centroids: &[Vec3], start: usize, end: usize, depth: usize) -> usize {
+        let node_index = self.nodes.len();
+        let count = end - start;
+
+        let mut node_bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for &index in &self.indices[start..end] {
+            node_bounds = node_bounds.union(&bounds[index]);
+            centroid_bounds = centroid_bounds.grow(&centroids[index]);
+        }
+        let area = node_bounds.surface_area();
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            first: start,
+            count,
+            axis: 0
+        });
+
+        let axis = centroid_bounds.largest_axis();
+        let min = centroid_bounds.min[axis];
+        let extent = centroid_bounds.max[axis] - min;
+        if count == 1 || depth >= MAX_DEPTH || extent <= 0.0 {
+            return node_index
+        }
+
+        // Sort the primitives into bins along the axis and find the boundary
+        // between bins that minimizes the surface area heuristic
+        let bin_of = |centroid: &Vec3| (((centroid[axis] - min) / extent * BINS as f32) as usize).min(BINS - 1);
+        let mut bin_bounds = vec![Aabb::empty(); BINS];
+        let mut bin_counts = [0usize; BINS];
+        for &index in &self.indices[start..end] {
+            let bin = bin_of(&centroids[index]);
+            bin_counts[bin] += 1;
+            bin_bounds[bin] = bin_bounds[bin].union(&bounds[index]);
+        }
+
+        let mut best_cost = f32::INFINITY;
+        let mut best_split = 0;
+        for split in 1..BINS {
+            let (left_count, right_count): (usize, usize) = (bin_counts[..split].iter().sum(), bin_counts[split..].iter().sum());
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+            let left = bin_bounds[..split].iter().fold(Aabb::empty(), |acc, bound| acc.union(bound));
+            let right = bin_bounds[split..].iter().fold(Aabb::empty(), |acc, bound| acc.union(bound));
+            let cost = left_count as f32 * left.surface_area() + right_count as f32 * right.surface_area();
+            if cost < best_cost {
+                best_cost = cost;
+                best_split = split;
+            }
+        }
+
+        // One traversal step plus the expected intersections, against testing everything here
+        let split_cost = 1.0 + best_cost / area.max(f32::MIN_POSITIVE);
+        if best_split == 0 || (count <= MAX_LEAF_SIZE && split_cost >= count as f32) {
+            return node_index
+        }
+
+        let mut mid = start;
+        for i in start..end {
+            if bin_of(&centroids[self.indices[i]]) < best_split {
+                self.indices.swap(i, mid);
+                mid += 1;
+            }
+        }
+
+        self.build_node(bounds, centroids, start, mid, depth + 1);
+        let right = self.build_node(bounds, centroids, mid, end, depth + 1);
+
+        let node = &mut self.nodes[node_index];
+        node.first = right;
+        node.count = 0;
+        node.axis = axis;
+        node_index
+    }
+
+    // Calls `hit` with the index of every primitive the ray may reach before
+    // `t_max`, nearest nodes first. `hit` returns the distance of the closer
+    // intersection it found if any, which then shortens the search.
+    pub fn traverse<F: FnMut(usize, f32) -> Option<f32>>(&self, ray: &Ray, t_max: f32, hit: F) {
+        self.walk(ray, t_max, false, hit);
+    }
+
+    // Stops at the first primitive for which `hit` returns true
+    pub fn any_hit<F: FnMut(usize, f32) -> bool>(&self, ray: &Ray, t_max: f32, mut hit: F) -> bool {
+        self.walk(ray, t_max, true, |index, t_max| if hit(index, t_max) { Some(t_max) } else { None })
+    }
+
+    fn walk<F: FnMut(usize, f32) -> Option<f32>>(&self, ray: &Ray, mut t_max: f32, first_hit: bool, mut hit: F) -> bool {
+        let mut found = false;
+        for &index in &self.unbounded {
+            if let Some(distance) = hit(index, t_max) {
+                if first_hit {
+                    return true
+                }
+                t_max = distance;
+                found = true;
+            }
+        }
+        if self.nodes.is_empty() {
+            return found
+        }
+
+        let inverse_direction = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        let mut stack = [0usize; MAX_DEPTH + 2];
+        let mut stack_size = 1;
+
+        while stack_size > 0 {
+            stack_size -= 1;
+            let node_index = stack[stack_size];
+            let node = &self.nodes[node_index];
+            if node.bounds.hit(ray, &inverse_direction, t_max).is_none() {
+                continue;
+            }
+
+            if node.count > 0 {
+                for &index in &self.indices[node.first..node.first + node.count] {
+                    if let Some(distance) = hit(index, t_max) {
+                        if first_hit {
+                            return true
+                        }
+                        t_max = distance;
+                        found = true;
+                    }
+                }
+            } else if ray.direction[node.axis] > 0.0 {
+                stack[stack_size] = node.first;
+                stack[stack_size + 1] = node_index + 1;
+                stack_size += 2;
+            } else {
+                stack[stack_size] = node_index + 1;
+                stack[stack_size + 1] = node.first;
+                stack_size += 2;
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::{
+        sphere::Sphere,
+        material::Material,
+        hitable::{Hitable, HitInfos}
+    };
+
+    fn random_spheres(count: usize, rng: &mut StdRng) -> Vec<Sphere> {
+        let material = Material::white();
+        (0..count).map(|_| {
+            let center = Vec3::new(rng.gen_range(-50.0, 50.0), rng.gen_range(-50.0, 50.0), rng.gen_range(-50.0, 50.0));
+            Sphere::new(center, rng.gen_range(0.1, 1.0), material.clone())
+        }).collect()
+    }
+
+    fn random_rays(count: usize, rng: &mut StdRng) -> Vec<Ray> {
+        (0..count).map(|_| {
+            let direction = Vec3::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0));
+            Ray::new(Vec3::new(0.0, 0.0, 80.0), (direction - Vec3::new(0.0, 0.0, 1.5)).normalize())
+        }).collect()
+    }
+
+    fn linear_closest<'a>(spheres: &'a [Sphere], ray: &Ray) -> Option<HitInfos<'a>> {
+        HitInfos::get_closest(spheres.iter().map(|sphere| sphere.ray_intersect(ray, 0.0, f32::INFINITY)).collect())
+    }
+
+    fn bvh_closest<'a>(bvh: &Bvh, spheres: &'a [Sphere], ray: &Ray) -> Option<HitInfos<'a>> {
+        let mut closest: Option<HitInfos> = None;
+        bvh.traverse(ray, f32::INFINITY, |index, t_max| {
+            let hit = spheres[index].ray_intersect(ray, 0.0, t_max)?;
+            let distance = hit.hit_distance;
+            closest = Some(hit);
+            Some(distance)
+        });
+        closest
+    }
+
+    #[test]
+    fn bvh_finds_the_same_hits_as_a_linear_scan() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let spheres = random_spheres(500, &mut rng);
+        let bvh = Bvh::build(&spheres.iter().map(|sphere| sphere.bounding_box()).collect::<Vec<_>>());
+
+        for ray in random_rays(2000, &mut rng) {
+            let expected = linear_closest(&spheres, &ray).map(|hit| hit.hit_distance);
+            let found = bvh_closest(&bvh, &spheres, &ray).map(|hit| hit.hit_distance);
+            assert_eq!(expected, found);
+
+            let occluded = bvh.any_hit(&ray, f32::INFINITY, |index, t_max| spheres[index].occluded(&ray, 0.0, t_max));
+            assert_eq!(expected.is_some(), occluded);
+        }
+    }
+
+    // cargo test --release bvh_speedup -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bvh_speedup() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for &count in &[100, 1000, 10000] {
+            let spheres = random_spheres(count, &mut rng);
+            let rays = random_rays(20000, &mut rng);
+
+            let start = Instant::now();
+            let bvh = Bvh::build(&spheres.iter().map(|sphere| sphere.bounding_box()).collect::<Vec<_>>());
+            let build = start.elapsed();
+
+            let start = Instant::now();
+            let linear_hits = rays.iter().filter(|ray| linear_closest(&spheres, ray).is_some()).count();
+            let linear = start.elapsed();
+
+            let start = Instant::now();
+            let bvh_hits = rays.iter().filter(|ray| bvh_closest(&bvh, &spheres, ray).is_some()).count();
+            let traversal = start.elapsed();
+
+            let speedup = linear.as_secs_f64() / traversal.as_secs_f64();
+            assert_eq!(linear_hits, bvh_hits);
+            println!(
+                "{} spheres: linear {:?}, bvh {:?} (+{:?} build), {:.1}x faster",
+                count, linear, traversal, build, speedup
+            );
+            // A hundred spheres are too few to tell, the tree pays off from there on
+            if count >= 1000 {
+                assert!(speedup > 3.0, "{} spheres: bvh only {:.1}x faster", count, speedup);
+            }
+        }
+    }
+}
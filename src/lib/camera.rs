@@ -1,7 +1,5 @@
-use rand::Rng;
-
-use crate::lib::vec3::Vec3;
-use crate::lib::ray::Ray;
+use crate::vec3::Vec3;
+use crate::ray::Ray;
 
 pub struct Camera {
     pub position: Vec3,
@@ -33,6 +31,12 @@ impl Camera {
         }
     }
 
+    pub fn set_resolution(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.image_aspect_ration = width as f32 / height as f32;
+    }
+
     pub fn set_rotation_x(&mut self, angle: f32) {
         let c = angle.cos();
         let s = angle.sin();
@@ -72,12 +76,15 @@ impl Camera {
         );
     }
 
-    pub fn compute_camera(&self, i: usize, j: usize) -> Ray {
+    // Columns are the camera right, up and backward axes in world space
+    pub fn set_rotation_matrix(&mut self, matrix: [[f32; 3]; 3]) {
+        self.rotation_matrix = matrix;
+    }
+
+    // `jitter` is the position of the sample inside the pixel, in [0, 1)²
+    pub fn compute_camera(&self, i: usize, j: usize, jitter: (f32, f32)) -> Ray {
+        let (rand_x, rand_y) = jitter;
 
-        let mut rng = rand::thread_rng();
-        let rand_x: f32 = rng.gen(); 
-        let rand_y: f32 = rng.gen();
-        
         let norm_i = (i as f32 + rand_x) / self.width as f32;
         let norm_j = (j as f32 + rand_y) / self.height as f32;
 
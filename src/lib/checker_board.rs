@@ -1,14 +1,205 @@
-use crate::lib::{
+use crate::{
     vec3::Vec3,
     ray::Ray,
+    material::Material,
+    aabb::Aabb,
+    optics,
+    light::Light,
     hitable::{
         Hitable,
         HitInfos
     }
+};
+
+// Plane tiled with two alternating materials. Infinite unless given a
+// dimension, in which case it is a rectangle centered on `origin`.
+#[derive(Debug, Clone)]
+pub struct CheckerBoard {
+    pub origin: Vec3,
+    pub normal: Vec3,
+    pub tile_size: f32,
+    pub dimension: Option<(f32, f32)>,
+    pub materials: [Material; 2],
+    u_axis: Vec3,
+    v_axis: Vec3
+}
+
+impl CheckerBoard {
+    pub fn new(origin: Vec3, normal: Vec3, tile_size: f32, first: Material, second: Material) -> CheckerBoard {
+        let normal = normal.normalize();
+        let (u_axis, v_axis) = optics::orthonormal_basis(&normal);
+
+        CheckerBoard {
+            origin,
+            normal,
+            tile_size,
+            dimension: None,
+            materials: [first, second],
+            u_axis,
+            v_axis
+        }
+    }
+
+    // Restricts the board to a width x depth rectangle along its two tile axes
+    pub fn with_dimension(mut self, width: f32, depth: f32) -> CheckerBoard {
+        self.dimension = Some((width, depth));
+        self
+    }
+
+    // Turns the tiles (and the edges of a finite board) around the normal
+    pub fn with_rotation(mut self, angle: f32) -> CheckerBoard {
+        let (c, s) = (angle.cos(), angle.sin());
+        let u_axis = &self.u_axis * c + &self.v_axis * s;
+        let v_axis = &self.v_axis * c - &self.u_axis * s;
+        self.u_axis = u_axis;
+        self.v_axis = v_axis;
+        self
+    }
+
+    fn plane_distance(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
+        let denominator = Vec3::dot(&ray.direction, &self.normal);
+        if denominator.abs() < 1e-8 {
+            // Parallel to the plane
+            return None
+        }
+        let distance = Vec3::dot(&(&self.origin - &ray.origin), &self.normal) / denominator;
+        if distance < t_min || distance > t_max {
+            return None
+        }
+
+        let local = &ray.origin + &(&ray.direction * distance) - &self.origin;
+        let u = Vec3::dot(&local, &self.u_axis);
+        let v = Vec3::dot(&local, &self.v_axis);
+        if let Some((width, depth)) = self.dimension {
+            if u.abs() > width / 2.0 || v.abs() > depth / 2.0 {
+                return None
+            }
+        }
+        Some((distance, u, v))
+    }
+}
+
+impl Hitable for CheckerBoard {
+    fn ray_intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitInfos<'_>> {
+        let (hit_distance, u, v) = self.plane_distance(ray, t_min, t_max)?;
+
+        let tile = (u / self.tile_size).floor() as i64 + (v / self.tile_size).floor() as i64;
+        let material = &self.materials[tile.rem_euclid(2) as usize];
+
+        // The board has no inside, it faces whichever side the ray comes from
+        let normal = if Vec3::dot(&ray.direction, &self.normal) > 0.0 {
+            -&self.normal
+        } else {
+            self.normal.clone()
+        };
+
+        Some(HitInfos {
+            hit_point: &ray.origin + &(&ray.direction * hit_distance),
+            hit_distance,
+            normal,
+            material,
+            barycentric: None,
+            color: None
+        })
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.plane_distance(ray, t_min, t_max).is_some()
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let (width, depth) = match self.dimension {
+            Some(dimension) => dimension,
+            None => return Aabb::infinite()
+        };
+        let u = &self.u_axis * (width / 2.0);
+        let v = &self.v_axis * (depth / 2.0);
+        // Slightly thickened so an axis aligned board does not get a flat box
+        let thickness = &self.normal * 1e-4;
+
+        let mut bounds = Aabb::empty();
+        for corner in &[&u + &v, &u - &v, -&u + &v, -&u - &v] {
+            bounds = bounds.grow(&(&self.origin + corner + &thickness));
+            bounds = bounds.grow(&(&self.origin + corner - &thickness));
+        }
+        bounds
+    }
+
+    fn emitter(&self) -> Result<Option<Box<dyn Light>>, String> {
+        if self.materials.iter().any(|material| material.is_emissive()) {
+            return Err(String::from("checker boards cannot emit light, use a rectangle light instead"))
+        }
+        Ok(None)
+    }
 }
 
-struct ChackerBoars {
-    origin: Vec3,
-    dimension: (f32, f32),
-    
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Floor at y = 0 with unit tiles, red first and blue second. Its tile
+    // axes are +x and -z.
+    fn floor() -> CheckerBoard {
+        let red = Material::new(Vec3::new(1.0, 0.0, 0.0), 1.0, 1.0, 0.0, 0.0, 1.0, 0.0);
+        let blue = Material::new(Vec3::new(0.0, 0.0, 1.0), 1.0, 1.0, 0.0, 0.0, 1.0, 0.0);
+        CheckerBoard::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, red, blue)
+    }
+
+    // Whether the board is hit straight below (x, z), and by the red tile
+    fn looking_down(board: &CheckerBoard, x: f32, z: f32) -> Option<bool> {
+        let ray = Ray::new(Vec3::new(x, 1.0, z), Vec3::new(0.0, -1.0, 0.0));
+        board.ray_intersect(&ray, 0.0, f32::INFINITY).map(|hit| hit.material.color.x == 1.0)
+    }
+
+    #[test]
+    fn tiles_alternate_across_negative_coordinates() {
+        let board = floor();
+        assert_eq!(looking_down(&board, 0.5, -0.5), Some(true));
+        assert_eq!(looking_down(&board, -0.5, -0.5), Some(false));
+        assert_eq!(looking_down(&board, -0.5, 0.5), Some(true));
+        assert_eq!(looking_down(&board, -1.5, -0.5), Some(true));
+        assert_eq!(looking_down(&board, -1.5, 0.5), Some(false));
+    }
+
+    #[test]
+    fn dimensions_clip_the_board() {
+        let board = floor().with_dimension(2.0, 4.0);
+        assert!(looking_down(&board, 0.9, 1.9).is_some());
+        assert!(looking_down(&board, 1.1, 0.0).is_none());
+        assert!(looking_down(&board, 0.0, -2.1).is_none());
+    }
+
+    #[test]
+    fn rotations_turn_the_tiles_and_the_edges() {
+        let board = floor().with_rotation(std::f32::consts::FRAC_PI_2);
+        // The tile axes are now -z and -x
+        assert_eq!(looking_down(&board, 0.5, -0.5), Some(false));
+        assert_eq!(looking_down(&board, -0.5, -0.5), Some(true));
+
+        // A quarter turn of a square shows its corners along the axes
+        let square = floor().with_dimension(2.0, 2.0);
+        assert!(looking_down(&square, 1.2, 0.0).is_none());
+        assert!(looking_down(&square.with_rotation(std::f32::consts::FRAC_PI_4), 1.2, 0.0).is_some());
+    }
+
+    #[test]
+    fn parallel_rays_miss() {
+        let board = floor();
+        let above = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let inside = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(board.plane_distance(&above, 0.0, f32::INFINITY).is_none());
+        assert!(board.plane_distance(&inside, 0.0, f32::INFINITY).is_none());
+        assert!(!board.occluded(&above, 0.0, f32::INFINITY));
+    }
+
+    #[test]
+    fn only_finite_boards_are_bounded() {
+        assert!(!floor().bounding_box().is_finite());
+
+        let bounds = floor().with_dimension(2.0, 4.0).bounding_box();
+        assert!(bounds.is_finite());
+        assert!((bounds.min.x + 1.0).abs() < 1e-5 && (bounds.max.x - 1.0).abs() < 1e-5);
+        assert!((bounds.min.z + 2.0).abs() < 1e-5 && (bounds.max.z - 2.0).abs() < 1e-5);
+        assert!(bounds.min.y < 0.0 && bounds.max.y > 0.0 && bounds.max.y < 1e-3);
+    }
+}
@@ -0,0 +1,99 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::scene_file::SceneFileError;
+
+#[derive(Debug)]
+pub enum RenderError {
+    // The scene file could not be loaded
+    Scene(SceneFileError),
+    // The settings of the scene make rendering impossible
+    InvalidSettings(String),
+    // A worker thread panicked while rendering a tile
+    Worker(String),
+    // The image could not be written, the framebuffer itself is untouched
+    Save {
+        path: PathBuf,
+        error: io::Error
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::Scene(error) => write!(f, "{}", error),
+            RenderError::InvalidSettings(message) => write!(f, "invalid render settings: {}", message),
+            RenderError::Worker(message) => write!(f, "a render thread panicked: {}", message),
+            RenderError::Save { path, error } => write!(f, "could not save {}: {}", path.display(), error)
+        }
+    }
+}
+
+impl Error for RenderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RenderError::Scene(error) => Some(error),
+            RenderError::Save { error, .. } => Some(error),
+            RenderError::InvalidSettings(_) | RenderError::Worker(_) => None
+        }
+    }
+}
+
+impl From<SceneFileError> for RenderError {
+    fn from(error: SceneFileError) -> Self {
+        RenderError::Scene(error)
+    }
+}
+
+// File the OBJ, MTL, PLY or HDR loaders could not read
+#[derive(Debug)]
+pub enum ReadError {
+    Io {
+        path: PathBuf,
+        error: io::Error
+    },
+    // The line is given for the text formats
+    Format {
+        path: PathBuf,
+        line: Option<usize>,
+        message: String
+    }
+}
+
+impl ReadError {
+    pub fn io(path: &Path, error: io::Error) -> ReadError {
+        ReadError::Io {
+            path: path.to_path_buf(),
+            error
+        }
+    }
+
+    pub fn format(path: &Path, message: String) -> ReadError {
+        ReadError::Format {
+            path: path.to_path_buf(),
+            line: None,
+            message
+        }
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ReadError::Format { path, line: Some(line), message } => write!(f, "{}:{}: {}", path.display(), line, message),
+            ReadError::Format { path, line: None, message } => write!(f, "{}: {}", path.display(), message)
+        }
+    }
+}
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadError::Io { error, .. } => Some(error),
+            ReadError::Format { .. } => None
+        }
+    }
+}
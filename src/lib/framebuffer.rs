@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+use crate::vec3::Vec3;
+use crate::error::RenderError;
+
+// Linear float RGB image, one Vec3 per pixel stored row by row, along with
+// the number of samples each pixel received
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>,
+    pub sample_counts: Vec<u32>
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Vec3::new(0.0, 0.0, 0.0); width * height],
+            sample_counts: vec![0; width * height]
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> &Vec3 {
+        &self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Vec3) {
+        self.pixels[y * self.width + x] = color
+    }
+
+    pub fn sample_count(&self, x: usize, y: usize) -> u32 {
+        self.sample_counts[y * self.width + x]
+    }
+
+    pub fn set_sample_count(&mut self, x: usize, y: usize, count: u32) {
+        self.sample_counts[y * self.width + x] = count
+    }
+
+    // Maps the sample count of every pixel from blue (fewest) to red (most)
+    pub fn sample_heatmap(&self) -> Framebuffer {
+        let min = self.sample_counts.iter().copied().min().unwrap_or(0) as f32;
+        let max = self.sample_counts.iter().copied().max().unwrap_or(0) as f32;
+        let range = (max - min).max(1.0);
+
+        let mut heatmap = self.clone();
+        for (pixel, count) in heatmap.pixels.iter_mut().zip(&self.sample_counts) {
+            let t = (*count as f32 - min) / range;
+            *pixel = if t < 0.5 {
+                Vec3::new(0.0, 2.0 * t, 1.0 - 2.0 * t)
+            } else {
+                Vec3::new(2.0 * t - 1.0, 2.0 - 2.0 * t, 0.0)
+            };
+        }
+        heatmap
+    }
+
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.pixels.len() * 3);
+        for pixel in &self.pixels {
+            data.extend_from_slice(&Self::get_color(pixel));
+        }
+        data
+    }
+
+    // The framebuffer is left as it is if this fails, so it can be saved
+    // somewhere else (see fallback_path)
+    pub fn save_as_png<P: AsRef<Path>>(&self, path: P) -> Result<(), RenderError> {
+        let path = path.as_ref();
+        self.write_png(path).map_err(|error| RenderError::Save {
+            path: path.to_path_buf(),
+            error
+        })
+    }
+
+    fn write_png(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        let w = &mut BufWriter::new(file);
+
+        let mut encoder = png::Encoder::new(&mut *w, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+
+        writer.write_image_data(&self.to_rgb8())?;
+        drop(writer);
+        w.flush()
+    }
+
+    // Where to save an image that could not be written to `path`: the same
+    // file name in `directory`, followed by the process id and a number no
+    // file there has yet, so an earlier rescued image is never overwritten
+    pub fn fallback_path<P: AsRef<Path>, D: AsRef<Path>>(path: P, directory: D) -> PathBuf {
+        let path = path.as_ref();
+        let stem = path.file_stem().unwrap_or_else(|| "output".as_ref()).to_string_lossy();
+        let extension = path.extension().unwrap_or_else(|| "png".as_ref()).to_string_lossy();
+        (0..)
+            .map(|n| directory.as_ref().join(format!("{}-{}-{}.{}", stem, process::id(), n, extension)))
+            .find(|candidate| !candidate.exists())
+            .expect("a free file name")
+    }
+
+    fn get_color(color: &Vec3) -> [u8; 3] {
+        let max = color.x.max(color.y.max(color.z));
+
+        // Overexposed colors are scaled down to keep their hue instead of being clipped
+        let mut return_value = color.clone();
+        if max > 1.0 {
+            return_value = return_value * (1.0 / max)
+        }
+
+        let r = (return_value.x.clamp(0.0, 1.0) * 255.0) as u8;
+        let g = (return_value.y.clamp(0.0, 1.0) * 255.0) as u8;
+        let b = (return_value.z.clamp(0.0, 1.0) * 255.0) as u8;
+
+        [r, g, b]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_saves_keep_the_image() {
+        let mut framebuffer = Framebuffer::new(2, 2);
+        framebuffer.set(1, 1, Vec3::new(1.0, 0.5, 0.0));
+
+        let path = std::env::temp_dir().join("tiny-raytracer-missing-directory").join("image.png");
+        match framebuffer.save_as_png(&path) {
+            Err(RenderError::Save { path: failed, .. }) => assert_eq!(failed, path),
+            other => panic!("expected a save error, got {:?}", other)
+        }
+
+        let directory = std::env::temp_dir();
+        let fallback = Framebuffer::fallback_path(&path, &directory);
+        assert_eq!(fallback.parent(), Some(directory.as_path()));
+        assert_eq!(fallback.extension(), path.extension());
+        framebuffer.save_as_png(&fallback).unwrap();
+        assert_eq!(framebuffer.to_rgb8()[9..], [255, 127, 0]);
+
+        // A second rescue does not overwrite the first one
+        let second = Framebuffer::fallback_path(&path, &directory);
+        assert_ne!(second, fallback);
+        assert!(second.file_name().unwrap().to_string_lossy().starts_with("image-"));
+        std::fs::remove_file(fallback).unwrap();
+    }
+}
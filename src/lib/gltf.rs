@@ -0,0 +1,546 @@
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use gltf::{
+    buffer,
+    camera::Projection,
+    khr_lights_punctual::Kind,
+    material::AlphaMode,
+    mesh::Mode,
+    Gltf,
+    Node
+};
+
+use crate::{
+    vec3::Vec3,
+    material::{Material, Fresnel},
+    mesh::{TriangleMesh, MeshError},
+    light::{Light, Falloff, PointLight, DirectionalLight, SpotLight},
+    camera::Camera,
+    scene::Scene
+};
+
+#[derive(Debug)]
+pub struct GltfError {
+    pub path: PathBuf,
+    pub error: gltf::Error
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl Error for GltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+// Column major 4x4 matrix, as stored by glTF
+type Matrix = [[f32; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0]
+];
+
+// Perspective camera of the file, the resolution is chosen when rendering
+#[derive(Debug, Clone)]
+pub struct GltfCamera {
+    pub name: String,
+    pub position: Vec3,
+    // Columns are the right, up and backward axes, see Camera::set_rotation_matrix
+    pub rotation: [[f32; 3]; 3],
+    pub yfov: f32,
+    // Width over height the camera was made for, if the file gives one
+    pub aspect_ratio: Option<f32>
+}
+
+impl GltfCamera {
+    pub fn to_camera(&self, width: usize, height: usize) -> Camera {
+        let mut camera = Camera::new(self.position.clone(), width, height, self.yfov);
+        camera.set_rotation_matrix(self.rotation);
+        camera
+    }
+}
+
+// Everything imported from a glTF file, in world space. Whatever could not
+// be represented is described in `warnings`.
+#[derive(Default)]
+pub struct GltfScene {
+    pub meshes: Vec<TriangleMesh>,
+    pub lights: Vec<Box<dyn Light>>,
+    pub cameras: Vec<GltfCamera>,
+    pub warnings: Vec<String>
+}
+
+impl GltfScene {
+    // Adds the meshes and lights to `scene` and, if the file has a camera,
+    // replaces the camera of the scene keeping its resolution. Emissive meshes
+    // are also sampled as lights. Returns the warnings of the import, along
+    // with the ones about the cameras that could not be followed.
+    pub fn push_into(mut self, scene: &mut Scene) -> Vec<String> {
+        if let Some(camera) = self.cameras.first() {
+            let (width, height) = (scene.camera().width, scene.camera().height);
+            let resolution = width as f32 / height as f32;
+            if let Some(aspect_ratio) = camera.aspect_ratio {
+                if (aspect_ratio - resolution).abs() > 0.01 * resolution {
+                    self.warnings.push(format!("{}: made for an aspect ratio of {}, the {}x{} image keeps the vertical field of view",
+                        camera.name, aspect_ratio, width, height));
+                }
+            }
+            scene.set_camera(camera.to_camera(width, height));
+        }
+        for camera in self.cameras.iter().skip(1) {
+            self.warnings.push(format!("{}: only the first camera is used, ignored", camera.name));
+        }
+        for mesh in self.meshes {
+            scene.push_object(mesh);
+        }
+        for light in self.lights {
+            scene.push_light(light);
+        }
+        self.warnings
+    }
+}
+
+// Loads the default scene (or the first one) of a .gltf or .glb file. Node
+// transforms are applied to the meshes, cameras and KHR_lights_punctual lights.
+// The PBR metallic-roughness materials are approximated with the constant
+// factors, textures are not loaded.
+pub fn load_gltf<P: AsRef<Path>>(path: P) -> Result<GltfScene, GltfError> {
+    let path = path.as_ref();
+    let error = |error| GltfError {
+        path: path.to_path_buf(),
+        error
+    };
+    let Gltf { document, blob } = Gltf::open(path).map_err(error)?;
+    let buffers = gltf::import_buffers(&document, path.parent(), blob).map_err(error)?;
+
+    let mut importer = Importer {
+        buffers: &buffers,
+        materials: vec![],
+        // glTF default: white, fully metallic and rough
+        default_material: metallic_roughness(Vec3::new(1.0, 1.0, 1.0), 1.0, 1.0, 0.0, 1.5),
+        scene: GltfScene::default()
+    };
+    importer.materials = document.materials()
+        .map(|material| convert_material(&material, &mut importer.scene.warnings))
+        .collect();
+
+    match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => {
+            for node in scene.nodes() {
+                importer.visit(&node, &IDENTITY);
+            }
+        },
+        None => importer.scene.warnings.push(String::from("the file has no scene"))
+    }
+    Ok(importer.scene)
+}
+
+struct Importer<'a> {
+    buffers: &'a [buffer::Data],
+    materials: Vec<Material>,
+    default_material: Material,
+    scene: GltfScene
+}
+
+impl Importer<'_> {
+    fn visit(&mut self, node: &Node, parent: &Matrix) {
+        let world = multiply(parent, &node.transform().matrix());
+        let name = describe("node", node.index(), node.name());
+
+        if node.skin().is_some() {
+            self.scene.warnings.push(format!("{}: skinning is not supported, the mesh is left in its bind pose", name));
+        }
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                let primitive_name = format!("{} primitive {}", describe("mesh", mesh.index(), mesh.name()), primitive.index());
+                if primitive.morph_targets().len() > 0 {
+                    self.scene.warnings.push(format!("{}: morph targets are not supported", primitive_name));
+                }
+                let mut warnings = vec![];
+                match self.convert_primitive(&primitive, &world, &mut warnings) {
+                    Ok(triangles) => self.scene.meshes.push(triangles),
+                    Err(message) => self.scene.warnings.push(format!("{}: {}, skipped", primitive_name, message))
+                }
+                self.scene.warnings.extend(warnings.into_iter().map(|warning| format!("{}: {}", primitive_name, warning)));
+            }
+        }
+        if let Some(camera) = node.camera() {
+            match camera.projection() {
+                Projection::Perspective(perspective) => self.scene.cameras.push(GltfCamera {
+                    name: describe("camera", camera.index(), camera.name()),
+                    position: translation(&world),
+                    rotation: rotation(&world),
+                    yfov: perspective.yfov(),
+                    aspect_ratio: perspective.aspect_ratio()
+                }),
+                Projection::Orthographic(_) => {
+                    let camera_name = describe("camera", camera.index(), camera.name());
+                    self.scene.warnings.push(format!("{}: orthographic cameras are not supported, skipped", camera_name));
+                }
+            }
+        }
+        if let Some(light) = node.light() {
+            let position = translation(&world);
+            // Lights shine along the -z axis of their node
+            let direction = -column(&world, 2);
+            let color = Vec3::new(light.color()[0], light.color()[1], light.color()[2]);
+            let falloff = match light.range() {
+                Some(range) => range_falloff(range),
+                None => Falloff::InverseSquare
+            };
+            match light.kind() {
+                Kind::Point => {
+                    let point = PointLight::new(position, light.intensity()).with_color(color).with_falloff(falloff);
+                    self.scene.lights.push(Box::new(point));
+                },
+                Kind::Spot { inner_cone_angle, outer_cone_angle } => {
+                    let spot = SpotLight::new(position, direction, light.intensity(), outer_cone_angle)
+                        .with_inner_angle(inner_cone_angle)
+                        .with_color(color)
+                        .with_falloff(falloff);
+                    self.scene.lights.push(Box::new(spot));
+                },
+                Kind::Directional => {
+                    let directional = DirectionalLight::new(direction, light.intensity()).with_color(color);
+                    self.scene.lights.push(Box::new(directional));
+                }
+            }
+        }
+
+        for child in node.children() {
+            self.visit(&child, &world);
+        }
+    }
+
+    // Attributes that do not have one value per position are dropped with a
+    // warning, the mesh is kept without them
+    fn convert_primitive(&self, primitive: &gltf::Primitive, world: &Matrix, warnings: &mut Vec<String>) -> Result<TriangleMesh, String> {
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+        let positions: Vec<Vec3> = match reader.read_positions() {
+            Some(positions) => positions.map(|position| transform_point(world, position)).collect(),
+            None => return Err(String::from("no POSITION attribute"))
+        };
+        let vertices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect()
+        };
+        if let Some(index) = vertices.iter().find(|&&index| index as usize >= positions.len()) {
+            return Err(format!("index {} is out of the {} vertices", index, positions.len()))
+        }
+
+        let mut indices: Vec<[u32; 3]> = match primitive.mode() {
+            Mode::Triangles => vertices.chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect(),
+            // Every other triangle of a strip is flipped to keep the winding
+            Mode::TriangleStrip => vertices.windows(3).enumerate().map(|(i, triangle)| {
+                if i % 2 == 0 { [triangle[0], triangle[1], triangle[2]] } else { [triangle[1], triangle[0], triangle[2]] }
+            }).collect(),
+            Mode::TriangleFan => vertices.windows(2).skip(1).map(|edge| [vertices[0], edge[0], edge[1]]).collect(),
+            mode => return Err(format!("{:?} primitives are not supported", mode))
+        };
+        if determinant(world) < 0.0 {
+            // A mirroring transform turns the triangles inside out
+            for triangle in indices.iter_mut() {
+                triangle.swap(1, 2);
+            }
+        }
+
+        let material = match primitive.material().index() {
+            Some(index) => self.materials[index].clone(),
+            None => self.default_material.clone()
+        };
+        let mut mesh = TriangleMesh::new(positions, indices, material).map_err(|error| error.to_string())?;
+        let mut ignore = |attribute: &str, error: MeshError| {
+            warnings.push(format!("{} ignored, {}", attribute, error));
+        };
+
+        if let Some(normals) = reader.read_normals() {
+            let normals: Vec<Vec3> = normals.map(|normal| transform_normal(world, normal)).collect();
+            if let Err(error) = mesh.set_normals(normals) {
+                ignore("NORMAL", error);
+            }
+        }
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            if let Err(error) = mesh.set_uvs(uvs.into_f32().map(|[u, v]| (u, v)).collect()) {
+                ignore("TEXCOORD_0", error);
+            }
+        }
+        if let Some(colors) = reader.read_colors(0) {
+            // The vertex colors are multiplied by the base color of the material
+            let base = mesh.material.color.clone();
+            if let Err(error) = mesh.set_colors(colors.into_rgb_f32().map(|[r, g, b]| &base * Vec3::new(r, g, b)).collect()) {
+                ignore("COLOR_0", error);
+            }
+        }
+        if mesh.degenerate_normal_count() > 0 {
+            warnings.push(format!("{} normals have no direction, the face normal is used there", mesh.degenerate_normal_count()));
+        }
+        Ok(mesh)
+    }
+}
+
+// Maps the metallic-roughness model onto Material: smooth metals become
+// mirrors, rough ones keep a diffuse look, and KHR_materials_transmission
+// gives a dielectric refracting with KHR_materials_ior
+fn convert_material(material: &gltf::Material, warnings: &mut Vec<String>) -> Material {
+    let name = describe("material", material.index().unwrap_or(0), material.name());
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, alpha] = pbr.base_color_factor();
+    let metallic = pbr.metallic_factor();
+    let roughness = pbr.roughness_factor();
+
+    let mut transmission = material.transmission().map(|transmission| transmission.transmission_factor()).unwrap_or(0.0);
+    let mut refraction_index = material.ior().unwrap_or(1.5);
+    match material.alpha_mode() {
+        AlphaMode::Opaque => {},
+        AlphaMode::Blend if transmission == 0.0 => {
+            // Seen through without bending the rays
+            transmission = 1.0 - alpha;
+            refraction_index = 1.0;
+        },
+        AlphaMode::Blend => {},
+        AlphaMode::Mask => warnings.push(format!("{}: alpha masks are not supported, the material is opaque", name))
+    }
+
+    let has_textures = pbr.base_color_texture().is_some()
+        || pbr.metallic_roughness_texture().is_some()
+        || material.normal_texture().is_some()
+        || material.occlusion_texture().is_some()
+        || material.emissive_texture().is_some();
+    if has_textures {
+        warnings.push(format!("{}: textures are not supported, using the constant factors", name));
+    }
+    let [er, eg, eb] = material.emissive_factor();
+    let emission_strength = material.emissive_strength().unwrap_or(1.0);
+
+    metallic_roughness(Vec3::new(r, g, b), metallic, roughness, transmission, refraction_index)
+        .with_emission(Vec3::new(er, eg, eb), emission_strength)
+}
+
+fn metallic_roughness(color: Vec3, metallic: f32, roughness: f32, transmission: f32, refraction_index: f32) -> Material {
+    // Blinn-Phong exponent matching the width of the GGX highlight
+    let alpha_roughness = (roughness * roughness).max(1e-3);
+    let specular_exponent = (2.0 / (alpha_roughness * alpha_roughness) - 2.0).clamp(1.0, 2000.0);
+    let reflexion_factor = metallic * (1.0 - roughness);
+    let specular_albedo = (0.04 + 0.96 * metallic) * (1.0 - roughness);
+    let color_albedo = (1.0 - reflexion_factor) * (1.0 - transmission);
+
+    let material = Material::new(
+        color,
+        color_albedo,
+        specular_exponent,
+        specular_albedo,
+        reflexion_factor,
+        refraction_index,
+        transmission
+    );
+    if transmission > 0.0 && refraction_index != 1.0 {
+        material.with_fresnel(Fresnel::Exact)
+    } else {
+        material
+    }
+}
+
+// Inverse square falloff smoothly reaching 0 at `range`, as recommended by
+// KHR_lights_punctual
+fn range_falloff(range: f32) -> Falloff {
+    Falloff::custom(move |distance| {
+        let window = (1.0 - (distance / range).powi(4)).clamp(0.0, 1.0);
+        window / (distance * distance)
+    })
+}
+
+fn describe(kind: &str, index: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("{} `{}`", kind, name),
+        None => format!("{} {}", kind, index)
+    }
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [[0.0; 4]; 4];
+    for (column, result_column) in result.iter_mut().enumerate() {
+        for (row, value) in result_column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    result
+}
+
+fn column(matrix: &Matrix, index: usize) -> Vec3 {
+    Vec3::new(matrix[index][0], matrix[index][1], matrix[index][2])
+}
+
+fn translation(matrix: &Matrix) -> Vec3 {
+    column(matrix, 3)
+}
+
+// Orientation of the transform, without its scale
+fn rotation(matrix: &Matrix) -> [[f32; 3]; 3] {
+    let axes = [column(matrix, 0).normalize(), column(matrix, 1).normalize(), column(matrix, 2).normalize()];
+    let mut rotation = [[0.0; 3]; 3];
+    for (row, values) in rotation.iter_mut().enumerate() {
+        for (axis, value) in values.iter_mut().enumerate() {
+            *value = axes[axis][row];
+        }
+    }
+    rotation
+}
+
+fn determinant(matrix: &Matrix) -> f32 {
+    Vec3::dot(&column(matrix, 0), &Vec3::cross(&column(matrix, 1), &column(matrix, 2)))
+}
+
+fn transform_point(matrix: &Matrix, [x, y, z]: [f32; 3]) -> Vec3 {
+    column(matrix, 0) * x + column(matrix, 1) * y + column(matrix, 2) * z + column(matrix, 3)
+}
+
+// Normals go through the inverse transpose, which is the cofactor matrix up to
+// the determinant (only its sign matters once normalized)
+fn transform_normal(matrix: &Matrix, [x, y, z]: [f32; 3]) -> Vec3 {
+    let (a, b, c) = (column(matrix, 0), column(matrix, 1), column(matrix, 2));
+    let normal = Vec3::cross(&b, &c) * x + Vec3::cross(&c, &a) * y + Vec3::cross(&a, &b) * z;
+    (normal * determinant(matrix).signum()).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A triangle under a scaled parent node, a camera, a point light and a
+    // colored directional light, with its vertices in a separate .bin file
+    const SCENE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual", "KHR_materials_emissive_strength"],
+        "extensions": { "KHR_lights_punctual": { "lights": [
+            { "type": "point", "intensity": 28.0, "range": 28.0 },
+            { "type": "directional", "color": [1.0, 0.5, 0.5] }
+        ] } },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 2, 3, 4] }],
+        "nodes": [
+            { "name": "parent", "scale": [2.0, 2.0, 2.0], "children": [1] },
+            { "mesh": 0, "translation": [0.0, 0.0, -5.0] },
+            { "camera": 0, "translation": [0.0, 1.0, 3.0] },
+            { "translation": [1.0, 2.0, 3.0], "extensions": { "KHR_lights_punctual": { "light": 0 } } },
+            { "extensions": { "KHR_lights_punctual": { "light": 1 } } }
+        ],
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1 } }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "materials": [{
+            "name": "gold",
+            "pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.8, 0.3, 1.0], "metallicFactor": 1.0, "roughnessFactor": 0.0 },
+            "emissiveFactor": [1.0, 0.0, 0.0],
+            "extensions": { "KHR_materials_emissive_strength": { "emissiveStrength": 4.0 } }
+        }],
+        "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0] }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{ "uri": "triangle.bin", "byteLength": 36 }]
+    }"#;
+
+    #[test]
+    fn imports_transforms_cameras_lights_and_materials() {
+        let directory = std::env::temp_dir().join("tiny-raytracer-gltf");
+        fs::create_dir_all(&directory).unwrap();
+        let vertices: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter().flat_map(|value| value.to_le_bytes()).collect();
+        fs::write(directory.join("triangle.bin"), vertices).unwrap();
+        fs::write(directory.join("scene.gltf"), SCENE).unwrap();
+
+        let scene = load_gltf(directory.join("scene.gltf")).unwrap();
+
+        assert_eq!(scene.meshes.len(), 1);
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.triangle_count(), 1);
+        // Scaled by the parent after the translation of the child
+        assert_eq!((mesh.positions[1].x, mesh.positions[1].z), (2.0, -10.0));
+        assert_eq!(mesh.material.reflexion_factor, 1.0);
+        assert_eq!(mesh.material.color_albedo, 0.0);
+        assert_eq!(mesh.material.emission().x, 4.0);
+
+        assert_eq!(scene.cameras.len(), 1);
+        assert_eq!(scene.cameras[0].yfov, 0.8);
+        assert_eq!(scene.cameras[0].position.z, 3.0);
+
+        assert_eq!(scene.lights.len(), 2);
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let point = scene.lights[0].sample(&origin, &Vec3::new(0.0, 1.0, 0.0), (0.5, 0.5)).unwrap();
+        // 28 / 14, barely windowed by the range
+        assert!((point.radiance.x - 2.0).abs() < 1e-3, "{:?}", point.radiance);
+        assert!((point.distance - 14f32.sqrt()).abs() < 1e-5);
+        // The directional light shines along -z, so comes from +z
+        let sun = scene.lights[1].sample(&origin, &Vec3::new(0.0, 1.0, 0.0), (0.5, 0.5)).unwrap();
+        assert_eq!((sun.direction.z, sun.distance), (1.0, f32::INFINITY));
+        assert_eq!((sun.radiance.x, sun.radiance.y), (1.0, 0.5));
+
+        assert!(scene.warnings.is_empty(), "{:?}", scene.warnings);
+    }
+
+    #[test]
+    fn attributes_not_matching_the_positions_are_dropped() {
+        let directory = std::env::temp_dir().join("tiny-raytracer-gltf-attributes");
+        fs::create_dir_all(&directory).unwrap();
+        // Three positions followed by only two normals
+        let values = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+        fs::write(directory.join("triangle.bin"), values.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>()).unwrap();
+        fs::write(directory.join("scene.gltf"), r#"{
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0, "NORMAL": 1 } }] }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0] },
+                { "bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC3" }
+            ],
+            "bufferViews": [{ "buffer": 0, "byteLength": 36 }, { "buffer": 0, "byteOffset": 36, "byteLength": 24 }],
+            "buffers": [{ "uri": "triangle.bin", "byteLength": 60 }]
+        }"#).unwrap();
+
+        let scene = load_gltf(directory.join("scene.gltf")).unwrap();
+        assert_eq!(scene.meshes.len(), 1);
+        assert!(scene.meshes[0].normals.is_empty());
+        assert_eq!(scene.warnings.len(), 1, "{:?}", scene.warnings);
+        assert!(scene.warnings[0].contains("NORMAL ignored, a mesh of 3 positions cannot have 2 normals"), "{}", scene.warnings[0]);
+    }
+
+    #[test]
+    fn extra_cameras_and_other_aspect_ratios_are_reported() {
+        let directory = std::env::temp_dir().join("tiny-raytracer-gltf-cameras");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("scene.gltf"), r#"{
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0, 1] }],
+            "nodes": [{ "camera": 0 }, { "camera": 1, "translation": [0.0, 0.0, 5.0] }],
+            "cameras": [
+                { "name": "square", "type": "perspective", "perspective": { "yfov": 0.8, "aspectRatio": 1.0, "znear": 0.1 } },
+                { "name": "wide", "type": "perspective", "perspective": { "yfov": 0.5, "aspectRatio": 2.0, "znear": 0.1 } }
+            ]
+        }"#).unwrap();
+
+        let imported = load_gltf(directory.join("scene.gltf")).unwrap();
+        let mut scene = Scene::new(1, 1, Camera::new(Vec3::new(0.0, 0.0, 0.0), 16, 8, 1.0));
+        let warnings = imported.push_into(&mut scene);
+
+        assert_eq!(scene.camera().fov, 0.8);
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].starts_with("camera `square`: made for an aspect ratio of 1"), "{}", warnings[0]);
+        assert!(warnings[1].starts_with("camera `wide`: only the first camera is used"), "{}", warnings[1]);
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_scaling() {
+        let mut matrix = IDENTITY;
+        matrix[0][0] = 4.0;
+        let normal = transform_normal(&matrix, [1.0, 1.0, 0.0].map(|v: f32| v / 2f32.sqrt()));
+        let tangent = transform_point(&matrix, [1.0, -1.0, 0.0]);
+        assert!(Vec3::dot(&normal, &tangent).abs() < 1e-5);
+    }
+}
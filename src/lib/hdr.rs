@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::Path;
+
+use crate::vec3::Vec3;
+use crate::error::ReadError;
+
+// Linear radiance values, row by row from the top of the image
+#[derive(Debug, Clone)]
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>
+}
+
+// Loads a Radiance .hdr (RGBE) image, flat or run length encoded. Only the
+// usual "-Y height +X width" orientation is supported.
+pub fn load_hdr<P: AsRef<Path>>(path: P) -> Result<HdrImage, ReadError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|error| ReadError::io(path, error))?;
+    parse_hdr(&bytes).map_err(|message| ReadError::format(path, message))
+}
+
+fn parse_hdr(bytes: &[u8]) -> Result<HdrImage, String> {
+    let mut reader = ByteReader {
+        bytes,
+        position: 0
+    };
+
+    if !reader.line()?.starts_with("#?") {
+        return Err(String::from("not a Radiance .hdr file"))
+    }
+    loop {
+        let line = reader.line()?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format.trim() != "32-bit_rle_rgbe" {
+                return Err(format!("unsupported pixel format `{}`", format.trim()))
+            }
+        }
+    }
+
+    let resolution = reader.line()?;
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => match (height.parse::<usize>(), width.parse::<usize>()) {
+            (Ok(height), Ok(width)) if height > 0 && width > 0 => (height, width),
+            _ => return Err(format!("invalid resolution `{}`", resolution))
+        },
+        _ => return Err(format!("unsupported orientation `{}`, expected `-Y height +X width`", resolution))
+    };
+
+    // Refuse sizes the rest of the file cannot hold before allocating them
+    let pixel_count = width.checked_mul(height).ok_or_else(|| format!("the image is too large ({} x {})", width, height))?;
+    let remaining = bytes.len() - reader.position;
+    match height.checked_mul(min_scanline_size(width)) {
+        Some(size) if size <= remaining => {},
+        _ => return Err(format!("the file is too short for a {} x {} image", width, height))
+    }
+
+    let mut pixels = Vec::with_capacity(pixel_count);
+    let mut scanline = vec![[0u8; 4]; width];
+    for row in 0..height {
+        reader.scanline(&mut scanline).map_err(|message| format!("row {}: {}", row, message))?;
+        pixels.extend(scanline.iter().map(rgbe_to_rgb));
+    }
+
+    Ok(HdrImage {
+        width,
+        height,
+        pixels
+    })
+}
+
+// Fewest bytes a scanline of `width` pixels can take, run length encoded with
+// the longest runs when allowed, flat otherwise
+fn min_scanline_size(width: usize) -> usize {
+    if (8..0x8000).contains(&width) {
+        4 + 4 * 2 * width.div_ceil(127)
+    } else {
+        width.saturating_mul(4)
+    }
+}
+
+fn rgbe_to_rgb(&[r, g, b, e]: &[u8; 4]) -> Vec3 {
+    if e == 0 {
+        return Vec3::new(0.0, 0.0, 0.0)
+    }
+    let scale = 2f32.powi(e as i32 - 136);
+    Vec3::new(r as f32 * scale, g as f32 * scale, b as f32 * scale)
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl<'a> ByteReader<'a> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.bytes.get(self.position).ok_or_else(|| String::from("the file ends before the image does"))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn line(&mut self) -> Result<String, String> {
+        let rest = &self.bytes[self.position..];
+        let end = rest.iter().position(|&byte| byte == b'\n').ok_or_else(|| String::from("the header is not terminated"))?;
+        self.position += end + 1;
+        Ok(String::from_utf8_lossy(&rest[..end]).trim_end_matches('\r').to_string())
+    }
+
+    fn scanline(&mut self, scanline: &mut [[u8; 4]]) -> Result<(), String> {
+        let width = scanline.len();
+        let rest = &self.bytes[self.position..];
+        // Run length encoded scanlines start with 2, 2 and their width
+        let encoded = (8..0x8000).contains(&width)
+            && rest.len() >= 4
+            && rest[0] == 2 && rest[1] == 2
+            && ((rest[2] as usize) << 8 | rest[3] as usize) == width;
+        if !encoded {
+            for pixel in scanline.iter_mut() {
+                for channel in pixel.iter_mut() {
+                    *channel = self.byte()?;
+                }
+            }
+            return Ok(())
+        }
+
+        self.position += 4;
+        // Each channel is stored separately, as runs and literal spans
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = self.byte()? as usize;
+                let (count, run) = if count > 128 { (count - 128, true) } else { (count, false) };
+                if count == 0 || x + count > width {
+                    return Err(String::from("invalid run length"))
+                }
+                if run {
+                    let value = self.byte()?;
+                    for pixel in &mut scanline[x..x + count] {
+                        pixel[channel] = value;
+                    }
+                } else {
+                    for pixel in &mut scanline[x..x + count] {
+                        pixel[channel] = self.byte()?;
+                    }
+                }
+                x += count;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_flat_and_run_length_encoded_rows() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+        // A flat row of 1.0, 0.5, 0.25
+        for _ in 0..8 {
+            bytes.extend_from_slice(&[128, 64, 32, 129]);
+        }
+        // An encoded row: red is a run, green literals, blue and the exponent runs
+        bytes.extend_from_slice(&[2, 2, 0, 8]);
+        bytes.extend_from_slice(&[128 + 8, 128]);
+        bytes.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        bytes.extend_from_slice(&[128 + 8, 0]);
+        bytes.extend_from_slice(&[128 + 8, 130]);
+
+        let image = parse_hdr(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (8, 2));
+        assert_eq!((image.pixels[3].x, image.pixels[3].y, image.pixels[3].z), (1.0, 0.5, 0.25));
+        assert_eq!((image.pixels[10].x, image.pixels[10].y), (2.0, 0.5));
+
+        assert!(parse_hdr(b"#?RADIANCE\n\n+Y 2 +X 8\n").unwrap_err().contains("orientation"));
+        assert!(parse_hdr(b"#?RADIANCE\n\n-Y 3000000 +X 3000000\n\x02\x02").unwrap_err().contains("too short"));
+        assert!(parse_hdr(b"#?RADIANCE\n\n-Y 18446744073709551615 +X 2\n").unwrap_err().contains("too large"));
+    }
+}
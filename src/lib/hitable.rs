@@ -1,30 +1,55 @@
-use crate::lib::vec3::Vec3;
-use crate::lib::ray::Ray;
-use crate::lib::material::Material;
+use crate::vec3::Vec3;
+use crate::ray::Ray;
+use crate::material::Material;
+use crate::aabb::Aabb;
+use crate::light::Light;
 
 pub trait Hitable: Sync + Send {
-    fn ray_intersect(&self, ray: &Ray) -> Option<HitInfos>;
+    // Closest hit whose distance along the ray lies in [t_min, t_max]
+    fn ray_intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitInfos<'_>>;
+
+    // Whether anything is hit in [t_min, t_max], without building the HitInfos
+    fn occluded(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.ray_intersect(ray, t_min, t_max).is_some()
+    }
+
+    // Aabb::infinite() for unbounded objects, which are kept out of the BVH
+    fn bounding_box(&self) -> Aabb;
+
+    // Light sampling the emission of the object, registered with the object
+    // by the scene. None for objects that do not emit, an error for emitting
+    // objects that cannot be sampled.
+    fn emitter(&self) -> Result<Option<Box<dyn Light>>, String> {
+        Ok(None)
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct HitInfos {
+pub struct HitInfos<'a> {
     pub hit_point: Vec3,
-    pub exit_point: Vec3,
     pub hit_distance: f32,
     pub normal: Vec3,
-    pub material: Material
+    pub material: &'a Material,
+    // (u, v) of the hit inside a triangle, the point being (1 - u - v) * a + u * b + v * c
+    pub barycentric: Option<(f32, f32)>,
+    // Per vertex color of a mesh, replacing the color of the material
+    pub color: Option<Vec3>
 }
 
-impl HitInfos {
-    pub fn get_closest(infos: Vec<Option<HitInfos>>) -> Option<HitInfos> {
+impl<'a> HitInfos<'a> {
+    pub fn diffuse_color(&self) -> &Vec3 {
+        self.color.as_ref().unwrap_or(&self.material.color)
+    }
+
+    pub fn get_closest(infos: Vec<Option<HitInfos<'a>>>) -> Option<HitInfos<'a>> {
         let mut min = None;
         for info in infos {
-            if (&min).is_none() {
+            if min.is_none() {
                 min = info;
                 continue;
             }
             if let Some(inf) = info {
-                if inf.hit_distance <= min.clone().unwrap().hit_distance {
+                if min.as_ref().is_some_and(|min: &HitInfos| inf.hit_distance <= min.hit_distance) {
                     min = Some(inf)
                 }
             }
@@ -0,0 +1,350 @@
+use std::f32::consts::PI;
+
+use crate::{
+    vec3::Vec3,
+    ray::Ray,
+    hitable::HitInfos,
+    scene::Scene,
+    sampler::Sampler,
+    optics
+};
+
+// Computes the color carried back along a camera ray
+pub trait Integrator: Sync + Send {
+    fn radiance(&self, scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> Vec3;
+}
+
+// Light reaching a hit: (diffuse light, specular light)
+fn direct_lighting(scene: &Scene, ray: &Ray, hit: &HitInfos, sampler: &mut dyn Sampler) -> (Vec3, Vec3) {
+    let mut diffuse_light = Vec3::new(0.0, 0.0, 0.0);
+    let mut specular_light = Vec3::new(0.0, 0.0, 0.0);
+
+    for light in scene.lights() {
+        // Lights with an extent are averaged over several shadow rays
+        let samples = light.samples();
+        let weight = 1.0 / samples as f32;
+        for _ in 0..samples {
+            let sample = match light.sample(&hit.hit_point, &hit.normal, sampler.next_2d()) {
+                Some(sample) => sample,
+                None => continue
+            };
+            if scene.is_shadowed(hit, &sample) {
+                continue;
+            }
+            let radiance = sample.radiance * weight;
+
+            let dot = Vec3::dot(&sample.direction, &hit.normal);
+            diffuse_light = diffuse_light + &radiance * dot.max(0.0);
+
+            let specular_reflect_dir = optics::reflect(&sample.direction, &hit.normal);
+            specular_light = specular_light + &radiance * (Vec3::dot(&specular_reflect_dir, &ray.direction)).max(0.0).powf(hit.material.specular_exponent);
+        }
+    }
+    // The Lambertian BRDF is albedo / PI, the Phong lobe is scaled the same way
+    (diffuse_light * (1.0 / PI), specular_light * (1.0 / PI))
+}
+
+// Light from the environment reaching a diffuse hit along one direction
+// sampled toward the bright parts of the background. With `mis` the cosine
+// weighted bounces also reach the background, both are then weighted with the
+// power heuristic (multiple importance sampling).
+fn environment_lighting(scene: &Scene, ray: &Ray, hit: &HitInfos, sampler: &mut dyn Sampler, mis: bool) -> Vec3 {
+    let (direction, radiance, pdf) = scene.environment().sample(sampler.next_2d());
+    let cos = Vec3::dot(&direction, &facing_normal(ray, hit));
+    if pdf <= 0.0 || cos <= 0.0 || hit.material.color_albedo <= 0.0 {
+        return Vec3::new(0.0, 0.0, 0.0)
+    }
+    let origin = optics::offset_origin(&hit.hit_point, &hit.normal, &direction);
+    if scene.occluded(&Ray::new(origin, direction), f32::INFINITY) {
+        return Vec3::new(0.0, 0.0, 0.0)
+    }
+    let weight = if mis { power_heuristic(pdf, cos / PI) } else { 1.0 };
+    hit.diffuse_color() * radiance * (hit.material.color_albedo * cos / (PI * pdf) * weight)
+}
+
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    pdf * pdf / (pdf * pdf + other_pdf * other_pdf)
+}
+
+// The normal on the side the ray comes from
+fn facing_normal(ray: &Ray, hit: &HitInfos) -> Vec3 {
+    if Vec3::dot(&ray.direction, &hit.normal) > 0.0 { -&hit.normal } else { hit.normal.clone() }
+}
+
+// Classic recursive tracer: direct light from the lights and the environment
+// plus perfect reflection and refraction. Fast and noise free with point like
+// lights, used for previews.
+pub struct WhittedIntegrator;
+
+impl WhittedIntegrator {
+    fn cast_ray(&self, scene: &Scene, ray: &Ray, recurtion: usize, sampler: &mut dyn Sampler) -> Vec3 {
+        let closest = scene.intersect(ray);
+
+        let t_max = closest.as_ref().map_or(f32::INFINITY, |hit| hit.hit_distance);
+        if let Some(radiance) = scene.light_emission(ray, t_max) {
+            return radiance
+        }
+
+        if recurtion > scene.max_recurtion() || closest.is_none() {
+            return scene.background(&ray.direction)
+        }
+
+        let closest = closest.unwrap();
+        let material = closest.material;
+
+        let (diffuse_light, specular_light) = direct_lighting(scene, ray, &closest, sampler);
+
+        let reflect_dir = optics::reflect(&ray.direction, &closest.normal);
+        let reflect_origin = optics::offset_origin(&closest.hit_point, &closest.normal, &reflect_dir);
+        let reflect_ray = Ray::new(reflect_origin, reflect_dir);
+        let reflect_color = self.cast_ray(scene, &reflect_ray, recurtion + 1, sampler);
+
+        let (mut reflect_weight, mut refract_weight) = material.specular_weights(&ray.direction, &closest.normal);
+
+        let refract_color = match optics::refract(&ray.direction, &closest.normal, material.refraction_index) {
+            Some(refract_dir) if refract_weight > 0.0 => {
+                let refract_dir = refract_dir.normalize();
+                let refract_origin = optics::offset_origin(&closest.hit_point, &closest.normal, &refract_dir);
+                let refract_ray = Ray::new(refract_origin, refract_dir);
+                self.cast_ray(scene, &refract_ray, recurtion + 1, sampler)
+            },
+            Some(_) => Vec3::new(0.0, 0.0, 0.0),
+            None => {
+                // Total internal reflection, the transmitted light is reflected instead
+                reflect_weight += refract_weight;
+                refract_weight = 0.0;
+                Vec3::new(0.0, 0.0, 0.0)
+            }
+        };
+
+        let mut color = closest.diffuse_color().clone();
+
+        color = color * diffuse_light * material.color_albedo;
+
+        color = color + environment_lighting(scene, ray, &closest, sampler, false);
+
+        color = color + specular_light * material.specular_albedo;
+
+        color = color + reflect_color * reflect_weight;
+
+        color = color + refract_color * refract_weight;
+
+        color = color + material.emission();
+
+        color
+    }
+}
+
+impl Integrator for WhittedIntegrator {
+    fn radiance(&self, scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> Vec3 {
+        self.cast_ray(scene, ray, 0, sampler)
+    }
+}
+
+// Unidirectional path tracer. At every bounce the lights and the environment
+// are sampled directly (next event estimation), then a single lobe of the material is
+// picked to continue the path: a cosine weighted diffuse bounce, the mirror
+// reflection or the refraction. Paths longer than `roulette_depth` bounces are
+// randomly terminated (russian roulette) and at most max_recurtion long.
+pub struct PathIntegrator {
+    roulette_depth: usize
+}
+
+impl PathIntegrator {
+    pub fn new(roulette_depth: usize) -> PathIntegrator {
+        PathIntegrator {
+            roulette_depth
+        }
+    }
+}
+
+impl Default for PathIntegrator {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl Integrator for PathIntegrator {
+    fn radiance(&self, scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> Vec3 {
+        let mut color = Vec3::new(0.0, 0.0, 0.0);
+        let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        let mut ray = ray.clone();
+        // Lights reached by a diffuse bounce were already sampled directly. This
+        // covers the lights added to the scene and the emitting objects.
+        let mut sees_lights = true;
+        // Pdf of the last bounce if it was diffuse, the background it reaches
+        // was also sampled directly
+        let mut diffuse_pdf = None;
+
+        for depth in 0..=scene.max_recurtion() {
+            let hit = scene.intersect_sampled(&ray);
+            let t_max = hit.as_ref().map_or(f32::INFINITY, |(hit, _)| hit.hit_distance);
+            if let Some(radiance) = scene.light_emission(&ray, t_max) {
+                if sees_lights {
+                    color = color + &throughput * radiance;
+                }
+                break;
+            }
+            let (hit, sampled_emission) = match hit {
+                Some(hit) => hit,
+                None => {
+                    let weight = match diffuse_pdf {
+                        Some(pdf) => power_heuristic(pdf, scene.environment().pdf(&ray.direction.normalize())),
+                        None => 1.0
+                    };
+                    color = color + &throughput * scene.background(&ray.direction) * weight;
+                    break;
+                }
+            };
+            let material = hit.material;
+
+            // Objects that cannot be sampled are counted whichever bounce reaches them
+            if sees_lights || !sampled_emission {
+                color = color + &throughput * material.emission();
+            }
+
+            let (diffuse_light, specular_light) = direct_lighting(scene, &ray, &hit, sampler);
+            let direct = hit.diffuse_color() * diffuse_light * material.color_albedo
+                + specular_light * material.specular_albedo;
+            color = color + &throughput * direct;
+            color = color + &throughput * environment_lighting(scene, &ray, &hit, sampler, true);
+
+            let (reflect_weight, refract_weight) = material.specular_weights(&ray.direction, &hit.normal);
+            let diffuse_weight = material.color_albedo * hit.diffuse_color().max_component();
+            let total_weight = diffuse_weight + reflect_weight + refract_weight;
+            if total_weight <= 0.0 {
+                break;
+            }
+
+            // Each lobe is picked with a probability of its weight / total_weight
+            let lobe = sampler.next_1d() * total_weight;
+            let direction = if lobe < diffuse_weight {
+                // The cosine of the bounce cancels with the pdf, leaving the albedo
+                let facing_normal = facing_normal(&ray, &hit);
+                throughput = throughput * (hit.diffuse_color() * (material.color_albedo * total_weight / diffuse_weight));
+                sees_lights = false;
+                let direction = optics::cosine_hemisphere(&facing_normal, sampler.next_2d());
+                diffuse_pdf = Some(Vec3::dot(&direction, &facing_normal).max(0.0) / PI);
+                direction
+            } else if lobe < diffuse_weight + reflect_weight {
+                throughput = throughput * total_weight;
+                sees_lights = true;
+                diffuse_pdf = None;
+                optics::reflect(&ray.direction, &hit.normal)
+            } else {
+                throughput = throughput * total_weight;
+                sees_lights = true;
+                diffuse_pdf = None;
+                match optics::refract(&ray.direction, &hit.normal, material.refraction_index) {
+                    Some(refract_dir) => refract_dir.normalize(),
+                    None => optics::reflect(&ray.direction, &hit.normal)
+                }
+            };
+
+            if depth >= self.roulette_depth {
+                let survival = throughput.max_component().min(0.95);
+                if sampler.next_1d() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+
+            let origin = optics::offset_origin(&hit.hit_point, &hit.normal, &direction);
+            ray = Ray::new(origin, direction);
+        }
+        color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::Camera,
+        material::Material,
+        sphere::Sphere,
+        checker_board::CheckerBoard,
+        light::SphereLight,
+        hitable::Hitable,
+        aabb::Aabb,
+        background::{Environment, ConstantBackground}
+    };
+
+    // A sphere whose emission is not sampled, so only the bounces find it
+    struct Unsampled(Sphere);
+
+    impl Hitable for Unsampled {
+        fn ray_intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitInfos<'_>> {
+            self.0.ray_intersect(ray, t_min, t_max)
+        }
+
+        fn bounding_box(&self) -> Aabb {
+            self.0.bounding_box()
+        }
+    }
+
+    enum Emitter {
+        Light,
+        Sampled,
+        Unsampled
+    }
+
+    // Mean color of a wall lit by a sphere beside the view
+    fn lit_wall(emitter: Emitter) -> f32 {
+        let white = Material::new(Vec3::new(1.0, 1.0, 1.0), 1.0, 10.0, 0.0, 0.0, 1.0, 0.0);
+        let center = Vec3::new(1.5, 0.0, -4.0);
+        let emissive = Material::new(Vec3::new(0.0, 0.0, 0.0), 0.0, 10.0, 0.0, 0.0, 1.0, 0.0)
+            .with_emission(Vec3::new(1.0, 1.0, 1.0), 1.0);
+
+        let mut scene = Scene::new(256, 2, Camera::new(Vec3::new(0.0, 0.0, 0.0), 16, 16, 0.05));
+        scene.set_integrator(PathIntegrator::default());
+        scene.set_environment(Environment::new(ConstantBackground::new(Vec3::new(0.0, 0.0, 0.0))));
+        scene.push_object(CheckerBoard::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 1.0, white.clone(), white));
+        match emitter {
+            Emitter::Light => scene.push_light(SphereLight::new(center, 0.5, 1.0)),
+            Emitter::Sampled => scene.push_object(Sphere::new(center, 0.5, emissive)),
+            Emitter::Unsampled => scene.push_object(Unsampled(Sphere::new(center, 0.5, emissive)))
+        }
+
+        let framebuffer = scene.render().unwrap();
+        let mut sum = 0.0;
+        for y in 0..16 {
+            for x in 0..16 {
+                sum += framebuffer.get(x, y).x;
+            }
+        }
+        sum / 256.0
+    }
+
+    #[test]
+    fn explicit_and_implicit_light_sampling_agree() {
+        let explicit = lit_wall(Emitter::Light);
+        let implicit = lit_wall(Emitter::Unsampled);
+        assert!((explicit - implicit).abs() < 0.05 * explicit, "{} {}", explicit, implicit);
+    }
+
+    #[test]
+    fn emissive_objects_are_sampled_as_lights() {
+        let light = lit_wall(Emitter::Light);
+        let object = lit_wall(Emitter::Sampled);
+        assert!((light - object).abs() < 0.02 * light, "{} {}", light, object);
+    }
+
+    #[test]
+    fn both_integrators_are_lit_by_the_environment() {
+        // A wall reflecting half of a uniform white sky
+        let grey = Material::new(Vec3::new(1.0, 1.0, 1.0), 0.5, 10.0, 0.0, 0.0, 1.0, 0.0);
+        let mut scene = Scene::new(64, 2, Camera::new(Vec3::new(0.0, 0.0, 0.0), 8, 8, 0.05));
+        scene.set_environment(Environment::new(ConstantBackground::new(Vec3::new(1.0, 1.0, 1.0))));
+        scene.push_object(CheckerBoard::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 1.0, grey.clone(), grey));
+
+        for path in [false, true] {
+            if path {
+                scene.set_integrator(PathIntegrator::default());
+            }
+            let framebuffer = scene.render().unwrap();
+            let mean = (0..64).map(|i| framebuffer.get(i % 8, i / 8).x).sum::<f32>() / 64.0;
+            assert!((mean - 0.5).abs() < 0.02, "{} {}", path, mean);
+        }
+    }
+}
@@ -1,17 +1,712 @@
-use crate::lib::vec3::Vec3;
+use std::f32::consts::PI;
+use std::fmt;
+use std::sync::Arc;
 
-#[derive(Clone)]
-pub struct Light {
+use crate::{
+    vec3::Vec3,
+    ray::Ray,
+    mesh::TriangleMesh,
+    hitable::Hitable,
+    optics
+};
+
+// Light arriving at a point from one light, along `direction`. `radiance` is
+// the incident radiance divided by the pdf of picking `direction` over solid
+// angle; delta lights (point, spot, directional) give their irradiance on a
+// surface facing them. The integrators multiply it by the BRDF and the cosine
+// with the normal, no light applies either.
+#[derive(Debug, Clone)]
+pub struct LightSample {
+    // From the shaded point towards the light, normalized
+    pub direction: Vec3,
+    // Distance to the light along `direction`, infinite for distant lights
+    pub distance: f32,
+    pub radiance: Vec3
+}
+
+pub trait Light: Sync + Send {
+    // Light reaching `point` on a surface oriented by `normal`, None if the
+    // point cannot be lit by this light. `u` is a random point in [0, 1)² for
+    // lights that need sampling. See LightSample for what `radiance` holds:
+    // averaging radiance * cos over `u` gives the irradiance at the point.
+    fn sample(&self, point: &Vec3, normal: &Vec3, u: (f32, f32)) -> Option<LightSample>;
+
+    // Number of samples, each with its own shadow ray, averaged at every
+    // shading point. Only lights with an extent need more than one.
+    fn samples(&self) -> usize {
+        1
+    }
+
+    // Distance along the ray to the visible surface of the light and the
+    // radiance it emits toward the ray origin. Lights without a shape cannot
+    // be hit.
+    fn intersect(&self, _ray: &Ray) -> Option<(f32, Vec3)> {
+        None
+    }
+}
+
+impl Light for Box<dyn Light> {
+    fn sample(&self, point: &Vec3, normal: &Vec3, u: (f32, f32)) -> Option<LightSample> {
+        self.as_ref().sample(point, normal, u)
+    }
+
+    fn samples(&self) -> usize {
+        self.as_ref().samples()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        self.as_ref().intersect(ray)
+    }
+}
+
+// How the light of a point or spot light fades with the distance
+#[derive(Clone, Default)]
+pub enum Falloff {
+    // Physically based: the light spreads over a sphere growing with the
+    // square of the distance
+    #[default]
+    InverseSquare,
+    // The same intensity at any distance
+    Constant,
+    // Attenuation factor for a distance
+    Custom(Arc<dyn Fn(f32) -> f32 + Send + Sync>)
+}
+
+impl Falloff {
+    pub fn custom<F: Fn(f32) -> f32 + Send + Sync + 'static>(curve: F) -> Falloff {
+        Falloff::Custom(Arc::new(curve))
+    }
+
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        match self {
+            Falloff::InverseSquare => 1.0 / (distance * distance),
+            Falloff::Constant => 1.0,
+            Falloff::Custom(curve) => curve(distance)
+        }
+    }
+}
+
+impl fmt::Debug for Falloff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Falloff::InverseSquare => write!(f, "InverseSquare"),
+            Falloff::Constant => write!(f, "Constant"),
+            Falloff::Custom(_) => write!(f, "Custom")
+        }
+    }
+}
+
+// Shines equally in every direction from a point
+#[derive(Debug, Clone)]
+pub struct PointLight {
     pub position: Vec3,
-    pub intensity: f32
+    pub color: Vec3,
+    pub intensity: f32,
+    pub falloff: Falloff
 }
 
-impl Light {
-    pub fn new(position: Vec3, intensity: f32) -> Light {
-        Light {
+impl PointLight {
+    // White light, fading with the square of the distance
+    pub fn new(position: Vec3, intensity: f32) -> PointLight {
+        PointLight {
             position,
+            color: Vec3::new(1.0, 1.0, 1.0),
+            intensity,
+            falloff: Falloff::default()
+        }
+    }
+
+    pub fn with_color(mut self, color: Vec3) -> PointLight {
+        self.color = color;
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> PointLight {
+        self.falloff = falloff;
+        self
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: &Vec3, _normal: &Vec3, _u: (f32, f32)) -> Option<LightSample> {
+        let to_light = &self.position - point;
+        let distance = to_light.lenght();
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            radiance: &self.color * (self.intensity * self.falloff.attenuation(distance))
+        })
+    }
+}
+
+// Infinitely far light, like the sun: every point is lit from the same direction
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    // Direction the light travels in
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, intensity: f32) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalize(),
+            color: Vec3::new(1.0, 1.0, 1.0),
             intensity
         }
     }
+
+    pub fn with_color(mut self, color: Vec3) -> DirectionalLight {
+        self.color = color;
+        self
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: &Vec3, _normal: &Vec3, _u: (f32, f32)) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -&self.direction,
+            distance: f32::INFINITY,
+            radiance: &self.color * self.intensity
+        })
+    }
+}
+
+// Point light restricted to a cone around `direction`. The intensity is full
+// inside `inner_angle` and smoothly falls to 0 at `outer_angle`.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub falloff: Falloff
+}
+
+impl SpotLight {
+    // Hard edged cone of half angle `cone_angle`, in radians
+    pub fn new(position: Vec3, direction: Vec3, intensity: f32, cone_angle: f32) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            color: Vec3::new(1.0, 1.0, 1.0),
+            intensity,
+            inner_angle: cone_angle,
+            outer_angle: cone_angle,
+            falloff: Falloff::default()
+        }
+    }
+
+    // Starts fading out at `inner_angle` instead of the edge of the cone
+    pub fn with_inner_angle(mut self, inner_angle: f32) -> SpotLight {
+        self.inner_angle = inner_angle.min(self.outer_angle);
+        self
+    }
+
+    pub fn with_color(mut self, color: Vec3) -> SpotLight {
+        self.color = color;
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> SpotLight {
+        self.falloff = falloff;
+        self
+    }
+
+    fn attenuation(&self, cos_angle: f32) -> f32 {
+        let (cos_inner, cos_outer) = (self.inner_angle.cos(), self.outer_angle.cos());
+        if cos_angle >= cos_inner {
+            return 1.0
+        }
+        if cos_angle <= cos_outer {
+            return 0.0
+        }
+        let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: &Vec3, _normal: &Vec3, _u: (f32, f32)) -> Option<LightSample> {
+        let to_light = &self.position - point;
+        let distance = to_light.lenght();
+        let direction = to_light / distance;
+
+        let attenuation = self.attenuation(-Vec3::dot(&direction, &self.direction));
+        if attenuation <= 0.0 {
+            return None
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: &self.color * (self.intensity * attenuation * self.falloff.attenuation(distance))
+        })
+    }
+}
+
+// Sky and ground surrounding the scene, blending from `ground_color` below to
+// `sky_color` above along `up`. Sampled over the hemisphere of
+// the shaded surface, so it casts soft ambient occlusion.
+#[derive(Debug, Clone)]
+pub struct HemisphereLight {
+    pub up: Vec3,
+    pub sky_color: Vec3,
+    pub ground_color: Vec3
+}
+
+impl HemisphereLight {
+    pub fn new(up: Vec3, sky_color: Vec3, ground_color: Vec3) -> HemisphereLight {
+        HemisphereLight {
+            up: up.normalize(),
+            sky_color,
+            ground_color
+        }
+    }
 }
 
+impl Light for HemisphereLight {
+    fn sample(&self, _point: &Vec3, normal: &Vec3, u: (f32, f32)) -> Option<LightSample> {
+        let direction = optics::uniform_hemisphere(normal, u);
+        let t = 0.5 * (1.0 + Vec3::dot(&direction, &self.up));
+        let color = &self.ground_color + (&self.sky_color - &self.ground_color) * t;
+
+        // Divided by the pdf of the direction, 1 / (2 PI)
+        Some(LightSample {
+            direction,
+            distance: f32::INFINITY,
+            radiance: color * (2.0 * PI)
+        })
+    }
+}
+
+// Area lights emit `intensity` times `color` from every point of their
+// surface, so they cast soft shadows. Each shading point averages `samples`
+// points on the surface, the penumbrae converging as more rays are traced per
+// pixel.
+const DEFAULT_AREA_SAMPLES: usize = 4;
+
+// Radiance arriving at `point` from `light_point` on a surface of `area`
+// oriented by `light_normal`, over the pdf of 1 / area turned into solid
+// angle. Only the side the normal points to emits.
+fn area_sample(point: &Vec3, light_point: &Vec3, light_normal: &Vec3, area: f32, radiance: Vec3) -> Option<LightSample> {
+    let to_light = light_point - point;
+    let distance_squared = to_light.lenght_squared();
+    let distance = distance_squared.sqrt();
+    let direction = to_light / distance;
+
+    let cos_light = -Vec3::dot(&direction, light_normal);
+    if cos_light <= 0.0 {
+        return None
+    }
+    Some(LightSample {
+        direction,
+        distance,
+        radiance: radiance * (cos_light * area / distance_squared)
+    })
+}
+
+// Distance along the ray to the plane through `center` oriented by `normal`,
+// if the ray reaches it from the emitting side
+fn plane_intersect(ray: &Ray, center: &Vec3, normal: &Vec3) -> Option<f32> {
+    let denominator = Vec3::dot(&ray.direction, normal);
+    if denominator >= 0.0 {
+        return None
+    }
+    let distance = Vec3::dot(&(center - &ray.origin), normal) / denominator;
+    if distance > 0.0 { Some(distance) } else { None }
+}
+
+// Parallelogram centered on `center` with edges `edge_u` and `edge_v`,
+// emitting on the side of edge_u × edge_v
+#[derive(Debug, Clone)]
+pub struct RectangleLight {
+    pub center: Vec3,
+    pub edge_u: Vec3,
+    pub edge_v: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub samples: usize
+}
+
+impl RectangleLight {
+    pub fn new(center: Vec3, edge_u: Vec3, edge_v: Vec3, intensity: f32) -> RectangleLight {
+        RectangleLight {
+            center,
+            edge_u,
+            edge_v,
+            color: Vec3::new(1.0, 1.0, 1.0),
+            intensity,
+            samples: DEFAULT_AREA_SAMPLES
+        }
+    }
+
+    pub fn with_color(mut self, color: Vec3) -> RectangleLight {
+        self.color = color;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: usize) -> RectangleLight {
+        self.samples = samples.max(1);
+        self
+    }
+
+    fn normal(&self) -> Vec3 {
+        Vec3::cross(&self.edge_u, &self.edge_v).normalize()
+    }
+}
+
+impl Light for RectangleLight {
+    fn sample(&self, point: &Vec3, _normal: &Vec3, u: (f32, f32)) -> Option<LightSample> {
+        let light_point = &self.center + &self.edge_u * (u.0 - 0.5) + &self.edge_v * (u.1 - 0.5);
+        let area = Vec3::cross(&self.edge_u, &self.edge_v).lenght();
+        area_sample(point, &light_point, &self.normal(), area, &self.color * self.intensity)
+    }
+
+    fn samples(&self) -> usize {
+        self.samples
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        let distance = plane_intersect(ray, &self.center, &self.normal())?;
+        let local = &ray.origin + &ray.direction * distance - &self.center;
+        let inside = |edge: &Vec3| (Vec3::dot(&local, edge) / edge.lenght_squared()).abs() <= 0.5;
+        if inside(&self.edge_u) && inside(&self.edge_v) {
+            Some((distance, &self.color * self.intensity))
+        } else {
+            None
+        }
+    }
+}
+
+// Disk of `radius` around `center`, emitting on the side of `normal`
+#[derive(Debug, Clone)]
+pub struct DiskLight {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f32,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub samples: usize
+}
+
+impl DiskLight {
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, intensity: f32) -> DiskLight {
+        DiskLight {
+            center,
+            normal: normal.normalize(),
+            radius,
+            color: Vec3::new(1.0, 1.0, 1.0),
+            intensity,
+            samples: DEFAULT_AREA_SAMPLES
+        }
+    }
+
+    pub fn with_color(mut self, color: Vec3) -> DiskLight {
+        self.color = color;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: usize) -> DiskLight {
+        self.samples = samples.max(1);
+        self
+    }
+}
+
+impl Light for DiskLight {
+    fn sample(&self, point: &Vec3, _normal: &Vec3, u: (f32, f32)) -> Option<LightSample> {
+        let r = self.radius * u.0.sqrt();
+        let phi = 2.0 * PI * u.1;
+        let (tangent, bitangent) = optics::orthonormal_basis(&self.normal);
+        let light_point = &self.center + tangent * (r * phi.cos()) + bitangent * (r * phi.sin());
+        let area = PI * self.radius * self.radius;
+        area_sample(point, &light_point, &self.normal, area, &self.color * self.intensity)
+    }
+
+    fn samples(&self) -> usize {
+        self.samples
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        let distance = plane_intersect(ray, &self.center, &self.normal)?;
+        let local = &ray.origin + &ray.direction * distance - &self.center;
+        if local.lenght_squared() <= self.radius * self.radius {
+            Some((distance, &self.color * self.intensity))
+        } else {
+            None
+        }
+    }
+}
+
+// Sphere emitting from its whole surface. Sampled over the cone it covers as
+// seen from the shading point rather than over its area, which wastes no
+// samples on the hidden side.
+#[derive(Debug, Clone)]
+pub struct SphereLight {
+    pub center: Vec3,
+    pub radius: f32,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub samples: usize
+}
+
+impl SphereLight {
+    pub fn new(center: Vec3, radius: f32, intensity: f32) -> SphereLight {
+        SphereLight {
+            center,
+            radius,
+            color: Vec3::new(1.0, 1.0, 1.0),
+            intensity,
+            samples: DEFAULT_AREA_SAMPLES
+        }
+    }
+
+    pub fn with_color(mut self, color: Vec3) -> SphereLight {
+        self.color = color;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: usize) -> SphereLight {
+        self.samples = samples.max(1);
+        self
+    }
+
+    // Distance along a normalized direction from `origin` to where it enters the sphere
+    fn entry_distance(&self, origin: &Vec3, direction: &Vec3) -> Option<f32> {
+        let oc = origin - &self.center;
+        let half_b = Vec3::dot(&oc, direction);
+        let c = oc.lenght_squared() - self.radius * self.radius;
+        let discriminant = half_b * half_b - c;
+        if discriminant < 0.0 {
+            return None
+        }
+        Some(-half_b - discriminant.sqrt())
+    }
+}
+
+impl Light for SphereLight {
+    fn sample(&self, point: &Vec3, _normal: &Vec3, u: (f32, f32)) -> Option<LightSample> {
+        let to_center = &self.center - point;
+        let distance_squared = to_center.lenght_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return None
+        }
+        let cos_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let direction = optics::uniform_cone(&(to_center / distance_squared.sqrt()), cos_max, u);
+        // Directions grazing the sphere can miss it by rounding
+        let distance = self.entry_distance(point, &direction).unwrap_or_else(|| (distance_squared - radius_squared).sqrt());
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: &self.color * (self.intensity * 2.0 * PI * (1.0 - cos_max))
+        })
+    }
+
+    fn samples(&self) -> usize {
+        self.samples
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        let direction_lenght = ray.direction.lenght();
+        let distance = self.entry_distance(&ray.origin, &(&ray.direction / direction_lenght))?;
+        if distance > 0.0 {
+            Some((distance / direction_lenght, &self.color * self.intensity))
+        } else {
+            None
+        }
+    }
+}
+
+// Triangle mesh giving off the emission of its material from both sides of
+// every triangle. Like the other area lights it only emits: it neither
+// reflects light nor casts shadows, emissive meshes pushed as objects are
+// sampled through an ObjectLight instead. Triangles are picked in proportion
+// to their area.
+#[derive(Debug, Clone)]
+pub struct MeshLight {
+    pub mesh: TriangleMesh,
+    pub samples: usize,
+    // Running sum of the triangle areas
+    cumulative_areas: Vec<f32>
+}
+
+impl MeshLight {
+    pub fn new(mesh: TriangleMesh) -> MeshLight {
+        let mut total = 0.0;
+        let cumulative_areas = mesh.indices.iter().map(|&[a, b, c]| {
+            let (a, b, c) = (&mesh.positions[a as usize], &mesh.positions[b as usize], &mesh.positions[c as usize]);
+            total += 0.5 * Vec3::cross(&(b - a), &(c - a)).lenght();
+            total
+        }).collect();
+        MeshLight {
+            mesh,
+            samples: DEFAULT_AREA_SAMPLES,
+            cumulative_areas
+        }
+    }
+
+    pub fn with_samples(mut self, samples: usize) -> MeshLight {
+        self.samples = samples.max(1);
+        self
+    }
+
+    pub fn area(&self) -> f32 {
+        self.cumulative_areas.last().copied().unwrap_or(0.0)
+    }
+}
+
+impl Light for MeshLight {
+    fn sample(&self, point: &Vec3, _normal: &Vec3, u: (f32, f32)) -> Option<LightSample> {
+        let area = self.area();
+        if area <= 0.0 {
+            return None
+        }
+        // The first random number picks the triangle, then is stretched back
+        // to [0, 1) over that triangle
+        let target = u.0 * area;
+        let index = self.cumulative_areas.partition_point(|&sum| sum <= target).min(self.cumulative_areas.len() - 1);
+        let start = if index > 0 { self.cumulative_areas[index - 1] } else { 0.0 };
+        let triangle_area = self.cumulative_areas[index] - start;
+        let u0 = ((target - start) / triangle_area).clamp(0.0, 1.0);
+
+        let [a, b, c] = self.mesh.indices[index];
+        let (a, b, c) = (&self.mesh.positions[a as usize], &self.mesh.positions[b as usize], &self.mesh.positions[c as usize]);
+        let root = u0.sqrt();
+        let (wa, wb) = (1.0 - root, u.1 * root);
+        let light_point = a * wa + b * wb + c * (1.0 - wa - wb);
+        let light_normal = Vec3::cross(&(b - a), &(c - a)).normalize();
+
+        let to_light = &light_point - point;
+        let distance_squared = to_light.lenght_squared();
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let cos_light = Vec3::dot(&direction, &light_normal).abs();
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.mesh.material.emission() * (cos_light * area / distance_squared)
+        })
+    }
+
+    fn samples(&self) -> usize {
+        self.samples
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        let hit = self.mesh.ray_intersect(ray, 0.0, f32::INFINITY)?;
+        Some((hit.hit_distance, self.mesh.material.emission()))
+    }
+}
+
+// Samples the emission of an object of the scene. The object itself is hit by
+// the rays and casts the shadows, so unlike the light it wraps this one has no
+// shape to intersect.
+#[derive(Debug, Clone)]
+pub struct ObjectLight<T: Light> {
+    pub light: T
+}
+
+impl<T: Light> ObjectLight<T> {
+    pub fn new(light: T) -> ObjectLight<T> {
+        ObjectLight {
+            light
+        }
+    }
+}
+
+impl<T: Light> Light for ObjectLight<T> {
+    fn sample(&self, point: &Vec3, normal: &Vec3, u: (f32, f32)) -> Option<LightSample> {
+        self.light.sample(point, normal, u)
+    }
+
+    fn samples(&self) -> usize {
+        self.light.samples()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+
+    #[test]
+    fn point_lights_fade_with_the_distance() {
+        let light = PointLight::new(Vec3::new(0.0, 4.0, 0.0), 32.0).with_color(Vec3::new(1.0, 0.5, 0.0));
+        let sample = light.sample(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, 1.0, 0.0), (0.5, 0.5)).unwrap();
+        assert_eq!((sample.radiance.x, sample.radiance.y, sample.radiance.z), (2.0, 1.0, 0.0));
+
+        let light = light.with_falloff(Falloff::custom(|distance| 1.0 / distance));
+        let sample = light.sample(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(0.0, 1.0, 0.0), (0.5, 0.5)).unwrap();
+        assert_eq!(sample.radiance.x, 8.0);
+    }
+
+    #[test]
+    fn area_lights_match_a_point_light_from_afar() {
+        let point = Vec3::new(0.0, 0.0, 0.0);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let center = Vec3::new(0.0, 100.0, 0.0);
+        // Same power toward the point: intensity times the projected area
+        let rectangle = RectangleLight::new(center.clone(), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 2.0), 1.0);
+        let sphere = SphereLight::new(center, 1.0, 4.0 / PI);
+
+        for light in [&rectangle as &dyn Light, &sphere] {
+            let sample = light.sample(&point, &normal, (0.3, 0.7)).unwrap();
+            assert!((sample.radiance.x - 4e-4).abs() < 1e-6, "{:?}", sample.radiance);
+            assert!((sample.distance - 99.0).abs() < 1.01);
+        }
+    }
+
+    #[test]
+    fn samples_average_to_the_irradiance() {
+        // Under a uniform sky of radiance L, the irradiance is PI L
+        let light = HemisphereLight::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.5, 0.5, 0.5), Vec3::new(0.5, 0.5, 0.5));
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let n = 64;
+        let irradiance = (0..n * n).map(|i| {
+            let u = ((i / n) as f32 / n as f32 + 0.5 / n as f32, ((i % n) as f32 + 0.5) / n as f32);
+            let sample = light.sample(&Vec3::new(0.0, 0.0, 0.0), &normal, u).unwrap();
+            sample.radiance.x * Vec3::dot(&sample.direction, &normal)
+        }).sum::<f32>() / (n * n) as f32;
+        assert!((irradiance - 0.5 * PI).abs() < 0.01, "{}", irradiance);
+
+        // A small disk of radiance L seen head on from d away: L area / d²
+        let disk = DiskLight::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.1, 2.0);
+        let sample = disk.sample(&Vec3::new(0.0, 0.0, 0.0), &normal, (0.5, 0.5)).unwrap();
+        assert!((sample.radiance.x - 2.0 * PI * 0.01 / 100.0).abs() < 1e-5, "{:?}", sample.radiance);
+    }
+
+    #[test]
+    fn area_lights_are_visible() {
+        let light = DiskLight::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.5, 3.0);
+        let up = Ray::new(Vec3::new(0.2, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(light.intersect(&up).map(|(distance, radiance)| (distance, radiance.x)), Some((2.0, 3.0)));
+        // Past the edge, and from behind
+        assert!(light.intersect(&Ray::new(Vec3::new(0.6, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0))).is_none());
+        assert!(light.intersect(&Ray::new(Vec3::new(0.0, 4.0, 0.0), Vec3::new(0.0, -1.0, 0.0))).is_none());
+    }
+
+    #[test]
+    fn mesh_lights_pick_triangles_by_area() {
+        let material = Material::new(Vec3::new(0.0, 0.0, 0.0), 0.0, 1.0, 0.0, 0.0, 1.0, 0.0).with_emission(Vec3::new(1.0, 1.0, 1.0), 2.0);
+        // A unit triangle and one four times larger, side by side at y = 1
+        let positions = vec![
+            Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 1.0),
+            Vec3::new(2.0, 1.0, 0.0), Vec3::new(4.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 2.0)
+        ];
+        let light = MeshLight::new(TriangleMesh::new(positions, vec![[0, 1, 2], [3, 4, 5]], material).unwrap());
+        assert_eq!(light.area(), 2.5);
+
+        let point = Vec3::new(0.0, 0.0, 0.0);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        assert!(light.sample(&point, &normal, (0.1, 0.5)).unwrap().direction.x < 0.5);
+        assert!(light.sample(&point, &normal, (0.5, 0.5)).unwrap().direction.x > 0.5);
+
+        let hit = light.intersect(&Ray::new(Vec3::new(3.0, 0.0, 0.5), Vec3::new(0.0, 1.0, 0.0)));
+        assert_eq!(hit.map(|(distance, radiance)| (distance, radiance.x)), Some((1.0, 2.0)));
+    }
+}
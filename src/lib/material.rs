@@ -1,4 +1,12 @@
-use crate::lib::vec3::Vec3;
+use crate::vec3::Vec3;
+use crate::optics;
+
+// How a dielectric material splits light between reflection and refraction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fresnel {
+    Exact,
+    Schlick
+}
 
 #[derive(Debug, Clone)]
 pub struct Material {
@@ -8,7 +16,11 @@ pub struct Material {
     pub specular_albedo: f32,
     pub reflexion_factor: f32,
     pub refraction_index: f32,
-    pub transparency_factor: f32
+    pub transparency_factor: f32,
+    pub fresnel: Option<Fresnel>,
+    // Light given off by the surface, added to whatever it reflects
+    pub emission_color: Vec3,
+    pub emission_strength: f32
 }
 
 impl Material {
@@ -29,8 +41,51 @@ impl Material {
             specular_albedo,
             reflexion_factor,
             refraction_index,
-            transparency_factor
+            transparency_factor,
+            fresnel: None,
+            emission_color: Vec3::new(0.0, 0.0, 0.0),
+            emission_strength: 0.0
         }
     }
+
+    // Makes the material a dielectric: reflexion_factor + transparency_factor
+    // is shared between the reflected and refracted rays according to the
+    // Fresnel equations instead of using the two factors as they are
+    pub fn with_fresnel(mut self, fresnel: Fresnel) -> Material {
+        self.fresnel = Some(fresnel);
+        self
+    }
+
+    pub fn with_emission(mut self, color: Vec3, strength: f32) -> Material {
+        self.emission_color = color;
+        self.emission_strength = strength;
+        self
+    }
+
+    pub fn emission(&self) -> Vec3 {
+        &self.emission_color * self.emission_strength
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission_strength > 0.0 && self.emission_color.max_component() > 0.0
+    }
+
+    // Weights of the reflected and refracted rays for a ray hitting the surface
+    pub fn specular_weights(&self, impident: &Vec3, normal: &Vec3) -> (f32, f32) {
+        let reflectance = match self.fresnel {
+            Some(Fresnel::Exact) => optics::fresnel(impident, normal, self.refraction_index),
+            Some(Fresnel::Schlick) => optics::schlick(impident, normal, self.refraction_index),
+            None => return (self.reflexion_factor, self.transparency_factor)
+        };
+        let total = self.reflexion_factor + self.transparency_factor;
+        (total * reflectance, total * (1.0 - reflectance))
+    }
 }
 
+#[cfg(test)]
+impl Material {
+    // Plain white diffuse material shared by the tests of the primitives and loaders
+    pub fn white() -> Material {
+        Material::new(Vec3::new(1.0, 1.0, 1.0), 1.0, 1.0, 0.0, 0.0, 1.0, 0.0)
+    }
+}
@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{
+    vec3::Vec3,
+    ray::Ray,
+    material::Material,
+    aabb::Aabb,
+    bvh::Bvh,
+    triangle,
+    light::{Light, MeshLight, ObjectLight},
+    hitable::{Hitable, HitInfos}
+};
+
+#[derive(Debug)]
+pub enum MeshError {
+    // Per vertex attribute whose length does not match the positions
+    Count { attribute: &'static str, count: usize, positions: usize },
+    // Triangle using a vertex past the end of the positions
+    Index { triangle: usize, index: u32, positions: usize }
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::Count { attribute, count, positions } =>
+                write!(f, "a mesh of {} positions cannot have {} {}", positions, count, attribute),
+            MeshError::Index { triangle, index, positions } =>
+                write!(f, "triangle {} uses vertex {} of a mesh of {} positions", triangle, index, positions)
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+// Indexed triangle mesh sharing a single material. The triangles are kept in
+// their own BVH, the mesh then shows up in the scene as one bounded object.
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    pub positions: Vec<Vec3>,
+    // Empty, or one normal per position
+    pub normals: Vec<Vec3>,
+    // Empty, or one texture coordinate per position
+    pub uvs: Vec<(f32, f32)>,
+    // Empty, or one color per position used instead of the material color
+    pub colors: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
+    pub material: Material,
+    bounds: Aabb,
+    bvh: Bvh
+}
+
+impl TriangleMesh {
+    pub fn new(positions: Vec<Vec3>, indices: Vec<[u32; 3]>, material: Material) -> Result<TriangleMesh, MeshError> {
+        for (triangle, vertices) in indices.iter().enumerate() {
+            if let Some(&index) = vertices.iter().find(|&&index| index as usize >= positions.len()) {
+                return Err(MeshError::Index {
+                    triangle,
+                    index,
+                    positions: positions.len()
+                })
+            }
+        }
+
+        let triangle_bounds: Vec<Aabb> = indices.iter().map(|[a, b, c]| {
+            Aabb::empty()
+                .grow(&positions[*a as usize])
+                .grow(&positions[*b as usize])
+                .grow(&positions[*c as usize])
+        }).collect();
+        let bounds = triangle_bounds.iter().fold(Aabb::empty(), |acc, bound| acc.union(bound));
+
+        Ok(TriangleMesh {
+            bvh: Bvh::build(&triangle_bounds),
+            positions,
+            normals: vec![],
+            uvs: vec![],
+            colors: vec![],
+            indices,
+            material,
+            bounds
+        })
+    }
+
+    // Smooth shading from one normal per position. Normals without a
+    // direction (zero or not finite) are kept as zero, the face normal is used
+    // where nothing else is left to interpolate.
+    pub fn set_normals(&mut self, normals: Vec<Vec3>) -> Result<(), MeshError> {
+        self.check_count("normals", normals.len())?;
+        self.normals = normals.iter().map(triangle::unit_or_zero).collect();
+        Ok(())
+    }
+
+    pub fn set_uvs(&mut self, uvs: Vec<(f32, f32)>) -> Result<(), MeshError> {
+        self.check_count("texture coordinates", uvs.len())?;
+        self.uvs = uvs;
+        Ok(())
+    }
+
+    pub fn set_colors(&mut self, colors: Vec<Vec3>) -> Result<(), MeshError> {
+        self.check_count("colors", colors.len())?;
+        self.colors = colors;
+        Ok(())
+    }
+
+    fn check_count(&self, attribute: &'static str, count: usize) -> Result<(), MeshError> {
+        if count != self.positions.len() {
+            return Err(MeshError::Count {
+                attribute,
+                count,
+                positions: self.positions.len()
+            })
+        }
+        Ok(())
+    }
+
+    // Vertex normals that were given without a direction
+    pub fn degenerate_normal_count(&self) -> usize {
+        self.normals.iter().filter(|normal| normal.lenght_squared() == 0.0).count()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn vertices(&self, index: usize) -> (&Vec3, &Vec3, &Vec3) {
+        let [a, b, c] = self.indices[index];
+        (&self.positions[a as usize], &self.positions[b as usize], &self.positions[c as usize])
+    }
+
+    fn vertex_normals(&self, index: usize) -> Option<(&Vec3, &Vec3, &Vec3)> {
+        if self.normals.is_empty() {
+            return None
+        }
+        let [a, b, c] = self.indices[index];
+        Some((&self.normals[a as usize], &self.normals[b as usize], &self.normals[c as usize]))
+    }
+
+    fn vertex_color(&self, index: usize, u: f32, v: f32) -> Option<Vec3> {
+        if self.colors.is_empty() {
+            return None
+        }
+        let [a, b, c] = self.indices[index];
+        Some(&self.colors[a as usize] * (1.0 - u - v) + &self.colors[b as usize] * u + &self.colors[c as usize] * v)
+    }
+}
+
+impl Hitable for TriangleMesh {
+    fn ray_intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitInfos<'_>> {
+        let mut closest = None;
+        self.bvh.traverse(ray, t_max, |index, t_max| {
+            let (a, b, c) = self.vertices(index);
+            let (distance, u, v) = triangle::intersect(ray, a, b, c, t_min, t_max)?;
+            closest = Some((index, distance, u, v));
+            Some(distance)
+        });
+
+        let (index, hit_distance, u, v) = closest?;
+        let (a, b, c) = self.vertices(index);
+
+        Some(HitInfos {
+            hit_point: &ray.origin + &(&ray.direction * hit_distance),
+            hit_distance,
+            normal: triangle::shading_normal(a, b, c, self.vertex_normals(index), u, v),
+            material: &self.material,
+            barycentric: Some((u, v)),
+            color: self.vertex_color(index, u, v)
+        })
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.bvh.any_hit(ray, t_max, |index, t_max| {
+            let (a, b, c) = self.vertices(index);
+            triangle::intersect(ray, a, b, c, t_min, t_max).is_some()
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds.clone()
+    }
+
+    // Only the triangles are needed to sample the mesh
+    fn emitter(&self) -> Result<Option<Box<dyn Light>>, String> {
+        if !self.material.is_emissive() {
+            return Ok(None)
+        }
+        let mesh = TriangleMesh::new(self.positions.clone(), self.indices.clone(), self.material.clone()).map_err(|error| error.to_string())?;
+        Ok(Some(Box::new(ObjectLight::new(MeshLight::new(mesh)))))
+    }
+}
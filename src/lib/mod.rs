@@ -1,10 +1,50 @@
 //pub mod renderer;
 
-pub mod scene;
-pub mod camera;
-pub mod vec3;
-pub mod ray;
-pub mod light;
-pub mod hitable;
-pub mod material;
-pub mod sphere;
\ No newline at end of file
+mod scene;
+mod camera;
+mod vec3;
+mod ray;
+mod light;
+mod background;
+mod hitable;
+mod material;
+mod sphere;
+mod checker_board;
+mod triangle;
+mod mesh;
+mod obj;
+mod ply;
+mod gltf;
+mod hdr;
+pub mod scene_file;
+mod error;
+mod tile;
+mod framebuffer;
+pub mod progress;
+mod sampler;
+mod adaptive;
+mod integrator;
+mod optics;
+mod aabb;
+mod bvh;
+
+pub use scene::Scene;
+pub use camera::Camera;
+pub use vec3::Vec3;
+pub use ray::Ray;
+pub use light::{Light, LightSample, Falloff, PointLight, DirectionalLight, SpotLight, HemisphereLight, RectangleLight, DiskLight, SphereLight, MeshLight, ObjectLight};
+pub use background::{Background, Environment, ConstantBackground, GradientBackground, EnvironmentMap};
+pub use hitable::{Hitable, HitInfos};
+pub use aabb::Aabb;
+pub use material::{Material, Fresnel};
+pub use sphere::Sphere;
+pub use checker_board::CheckerBoard;
+pub use triangle::Triangle;
+pub use mesh::{TriangleMesh, MeshError};
+pub use hdr::HdrImage;
+pub use framebuffer::Framebuffer;
+pub use progress::{Progress, RenderObserver, CancelToken};
+pub use sampler::{Sampler, SamplerKind};
+pub use adaptive::AdaptiveSampling;
+pub use integrator::{Integrator, WhittedIntegrator, PathIntegrator};
+pub use error::{RenderError, ReadError};
\ No newline at end of file
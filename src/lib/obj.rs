@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    vec3::Vec3,
+    material::Material,
+    mesh::TriangleMesh,
+    error::ReadError
+};
+
+// One mesh per group (g or o) and material (usemtl) combination of the file
+#[derive(Debug, Clone)]
+pub struct ObjGroup {
+    pub name: String,
+    pub mesh: TriangleMesh
+}
+
+// Loads the triangles of an OBJ file along with the MTL libraries it uses.
+// Faces without a material get `default_material`, faces with more than three
+// vertices are triangulated as fans.
+pub fn load_obj<P: AsRef<Path>>(path: P, default_material: &Material) -> Result<Vec<ObjGroup>, ReadError> {
+    let path = path.as_ref();
+    let source = read(path)?;
+    ObjParser::new(path, default_material).parse(&source)
+}
+
+// Parses the `newmtl` entries of an MTL file into materials
+pub fn load_mtl<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Material>, ReadError> {
+    let path = path.as_ref();
+    let source = read(path)?;
+    parse_mtl(path, &source)
+}
+
+fn read(path: &Path) -> Result<String, ReadError> {
+    fs::read_to_string(path).map_err(|error| ReadError::io(path, error))
+}
+
+// Splits a line into its keyword and arguments, dropping comments
+fn tokenize(line: &str) -> Option<(&str, Vec<&str>)> {
+    let line = line.split('#').next().unwrap_or("");
+    let mut tokens = line.split_whitespace();
+    let keyword = tokens.next()?;
+    Some((keyword, tokens.collect()))
+}
+
+struct LineContext<'a> {
+    path: &'a Path,
+    line: usize
+}
+
+impl LineContext<'_> {
+    fn error<T>(&self, message: String) -> Result<T, ReadError> {
+        Err(ReadError::Format {
+            path: self.path.to_path_buf(),
+            line: Some(self.line),
+            message
+        })
+    }
+
+    fn floats(&self, keyword: &str, arguments: &[&str], min: usize, max: usize) -> Result<Vec<f32>, ReadError> {
+        if arguments.len() < min || arguments.len() > max {
+            return self.error(format!("`{}` expects {} to {} numbers, got {}", keyword, min, max, arguments.len()))
+        }
+        arguments.iter().map(|argument| {
+            argument.parse::<f32>().or_else(|_| self.error(format!("`{}` is not a number", argument)))
+        }).collect()
+    }
+
+    fn vec3(&self, keyword: &str, arguments: &[&str]) -> Result<Vec3, ReadError> {
+        let values = self.floats(keyword, arguments, 3, 4)?;
+        Ok(Vec3::new(values[0], values[1], values[2]))
+    }
+}
+
+// Triangles of the group being read, with its own vertex numbering
+struct GroupBuilder {
+    name: String,
+    material_name: Option<String>,
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+    indices: Vec<[u32; 3]>,
+    // (position, uv, normal) indices of the file to the index in the group
+    vertices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+    has_normals: bool,
+    has_uvs: bool
+}
+
+impl GroupBuilder {
+    fn new(name: String, material_name: Option<String>) -> GroupBuilder {
+        GroupBuilder {
+            name,
+            material_name,
+            positions: vec![],
+            normals: vec![],
+            uvs: vec![],
+            indices: vec![],
+            vertices: HashMap::new(),
+            has_normals: true,
+            has_uvs: true
+        }
+    }
+}
+
+struct ObjParser<'a> {
+    path: &'a Path,
+    default_material: &'a Material,
+    materials: HashMap<String, Material>,
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+    groups: Vec<GroupBuilder>,
+    current: GroupBuilder
+}
+
+impl<'a> ObjParser<'a> {
+    fn new(path: &'a Path, default_material: &'a Material) -> ObjParser<'a> {
+        ObjParser {
+            path,
+            default_material,
+            materials: HashMap::new(),
+            positions: vec![],
+            normals: vec![],
+            uvs: vec![],
+            groups: vec![],
+            current: GroupBuilder::new(String::from("default"), None)
+        }
+    }
+
+    fn parse(mut self, source: &str) -> Result<Vec<ObjGroup>, ReadError> {
+        for (number, line) in source.lines().enumerate() {
+            let context = LineContext {
+                path: self.path,
+                line: number + 1
+            };
+            let (keyword, arguments) = match tokenize(line) {
+                Some(tokens) => tokens,
+                None => continue
+            };
+
+            match keyword {
+                "v" => self.positions.push(context.vec3(keyword, &arguments)?),
+                "vn" => self.normals.push(context.vec3(keyword, &arguments)?),
+                "vt" => {
+                    let values = context.floats(keyword, &arguments, 1, 3)?;
+                    self.uvs.push((values[0], values.get(1).copied().unwrap_or(0.0)));
+                },
+                "f" => self.face(&context, &arguments)?,
+                "g" | "o" => {
+                    let name = if arguments.is_empty() { String::from("default") } else { arguments.join(" ") };
+                    let material_name = self.current.material_name.clone();
+                    self.start_group(name, material_name);
+                },
+                "usemtl" => {
+                    let name = match arguments.first() {
+                        Some(name) => name.to_string(),
+                        None => return context.error(String::from("`usemtl` needs a material name"))
+                    };
+                    if !self.materials.contains_key(&name) {
+                        return context.error(format!("unknown material `{}`", name))
+                    }
+                    let group_name = self.current.name.clone();
+                    self.start_group(group_name, Some(name));
+                },
+                "mtllib" => {
+                    if arguments.is_empty() {
+                        return context.error(String::from("`mtllib` needs a file name"))
+                    }
+                    // Library paths are relative to the OBJ file
+                    let directory = self.path.parent().unwrap_or_else(|| Path::new(""));
+                    let library = load_mtl(directory.join(arguments.join(" ")))?;
+                    self.materials.extend(library);
+                },
+                // Smoothing groups, lines, points, free form geometry and
+                // vendor extensions are not rendered
+                _ => {}
+            }
+        }
+
+        let current = std::mem::replace(&mut self.current, GroupBuilder::new(String::new(), None));
+        self.groups.push(current);
+        Ok(self.finish())
+    }
+
+    fn start_group(&mut self, name: String, material_name: Option<String>) {
+        let previous = std::mem::replace(&mut self.current, GroupBuilder::new(name, material_name));
+        self.groups.push(previous);
+    }
+
+    // Resolves a 1 based, possibly negative (counted from the end) OBJ index
+    fn resolve(context: &LineContext, token: &str, count: usize, kind: &str) -> Result<usize, ReadError> {
+        let index: i64 = token.parse().or_else(|_| context.error(format!("`{}` is not a valid {} index", token, kind)))?;
+        let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return context.error(format!("{} index {} is out of range, {} defined so far", kind, index, count))
+        }
+        Ok(resolved as usize)
+    }
+
+    fn face(&mut self, context: &LineContext, arguments: &[&str]) -> Result<(), ReadError> {
+        if arguments.len() < 3 {
+            return context.error(format!("a face needs at least 3 vertices, got {}", arguments.len()))
+        }
+
+        let mut corners = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            // v, v/vt, v//vn or v/vt/vn
+            let mut parts = argument.split('/');
+            let position = Self::resolve(context, parts.next().unwrap_or(""), self.positions.len(), "position")?;
+            let uv = match parts.next() {
+                Some("") | None => None,
+                Some(token) => Some(Self::resolve(context, token, self.uvs.len(), "texture coordinate")?)
+            };
+            let normal = match parts.next() {
+                Some("") | None => None,
+                Some(token) => Some(Self::resolve(context, token, self.normals.len(), "normal")?)
+            };
+            corners.push(self.vertex(position, uv, normal));
+        }
+
+        for i in 1..corners.len() - 1 {
+            self.current.indices.push([corners[0], corners[i], corners[i + 1]]);
+        }
+        Ok(())
+    }
+
+    fn vertex(&mut self, position: usize, uv: Option<usize>, normal: Option<usize>) -> u32 {
+        let (positions, uvs, normals, group) = (&self.positions, &self.uvs, &self.normals, &mut self.current);
+        if let Some(&index) = group.vertices.get(&(position, uv, normal)) {
+            return index
+        }
+
+        let index = group.positions.len() as u32;
+        group.positions.push(positions[position].clone());
+        group.has_uvs &= uv.is_some();
+        group.uvs.push(uv.map(|uv| uvs[uv]).unwrap_or((0.0, 0.0)));
+        group.has_normals &= normal.is_some();
+        group.normals.push(normal.map(|normal| normals[normal].clone()).unwrap_or_else(|| Vec3::new(0.0, 0.0, 0.0)));
+        group.vertices.insert((position, uv, normal), index);
+        index
+    }
+
+    fn finish(self) -> Vec<ObjGroup> {
+        let mut groups = vec![];
+        for group in self.groups {
+            if group.indices.is_empty() {
+                continue;
+            }
+            let material = match &group.material_name {
+                Some(name) => self.materials[name].clone(),
+                None => self.default_material.clone()
+            };
+
+            // The indices, normals and uvs are pushed along with the positions,
+            // so they always match. Normals and uvs are only kept if every
+            // vertex of the group has one.
+            let mut mesh = TriangleMesh::new(group.positions, group.indices, material).expect("indices into the group positions");
+            if group.has_normals {
+                mesh.set_normals(group.normals).expect("one normal per vertex");
+            }
+            if group.has_uvs {
+                mesh.set_uvs(group.uvs).expect("one texture coordinate per vertex");
+            }
+
+            groups.push(ObjGroup {
+                name: group.name,
+                mesh
+            });
+        }
+        groups
+    }
+}
+
+fn parse_mtl(path: &Path, source: &str) -> Result<HashMap<String, Material>, ReadError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlEntry)> = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let context = LineContext {
+            path,
+            line: number + 1
+        };
+        let (keyword, arguments) = match tokenize(line) {
+            Some(tokens) => tokens,
+            None => continue
+        };
+
+        if keyword == "newmtl" {
+            if arguments.is_empty() {
+                return context.error(String::from("`newmtl` needs a material name"))
+            }
+            if let Some((name, entry)) = current.take() {
+                materials.insert(name, entry.to_material());
+            }
+            current = Some((arguments.join(" "), MtlEntry::default()));
+            continue;
+        }
+
+        let entry = match current.as_mut() {
+            Some((_, entry)) => entry,
+            None => return context.error(format!("`{}` before any `newmtl`", keyword))
+        };
+        match keyword {
+            "Kd" => entry.diffuse = context.vec3(keyword, &arguments)?,
+            "Ks" => entry.specular = context.vec3(keyword, &arguments)?,
+            "Ke" => entry.emission = context.vec3(keyword, &arguments)?,
+            "Ns" => entry.shininess = context.floats(keyword, &arguments, 1, 1)?[0],
+            "Ni" => entry.refraction_index = context.floats(keyword, &arguments, 1, 1)?[0],
+            "d" => entry.dissolve = context.floats(keyword, &arguments, 1, 1)?[0],
+            "Tr" => entry.dissolve = 1.0 - context.floats(keyword, &arguments, 1, 1)?[0],
+            "illum" => entry.illumination = context.floats(keyword, &arguments, 1, 1)?[0] as u32,
+            // Ambient color, textures and the other statements have no equivalent in Material
+            _ => {}
+        }
+    }
+    if let Some((name, entry)) = current {
+        materials.insert(name, entry.to_material());
+    }
+    Ok(materials)
+}
+
+struct MtlEntry {
+    diffuse: Vec3,
+    specular: Vec3,
+    emission: Vec3,
+    shininess: f32,
+    refraction_index: f32,
+    dissolve: f32,
+    illumination: u32
+}
+
+impl Default for MtlEntry {
+    fn default() -> Self {
+        MtlEntry {
+            diffuse: Vec3::new(0.8, 0.8, 0.8),
+            specular: Vec3::new(0.0, 0.0, 0.0),
+            emission: Vec3::new(0.0, 0.0, 0.0),
+            shininess: 10.0,
+            refraction_index: 1.0,
+            dissolve: 1.0,
+            illumination: 2
+        }
+    }
+}
+
+impl MtlEntry {
+    fn to_material(&self) -> Material {
+        let specular = self.specular.max_component();
+        // Illumination models 3 and up add ray traced reflections
+        let reflexion_factor = if self.illumination >= 3 { specular } else { 0.0 };
+        Material::new(
+            self.diffuse.clone(),
+            1.0,
+            self.shininess,
+            specular,
+            reflexion_factor,
+            self.refraction_index,
+            (1.0 - self.dissolve).clamp(0.0, 1.0)
+        ).with_emission(self.emission.clone(), 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{ray::Ray, hitable::Hitable};
+
+    fn write_files(name: &str, obj: &str, mtl: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("tiny-raytracer-obj-{}", name));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("model.mtl"), mtl).unwrap();
+        fs::write(directory.join("model.obj"), obj).unwrap();
+        directory.join("model.obj")
+    }
+
+    #[test]
+    fn groups_polygons_and_materials() {
+        let path = write_files("groups", "\
+mtllib model.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+o quad
+usemtl glass
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g wedge
+f -5 -4 -1
+", "\
+newmtl glass
+Kd 0.1 0.2 0.3
+Ke 0 0 0.5
+Ns 120
+Ni 1.5
+d 0.25
+");
+
+        let groups = load_obj(&path, &Material::white()).unwrap();
+        assert_eq!(groups.len(), 2);
+
+        let quad = &groups[0];
+        assert_eq!(quad.name, "quad");
+        assert_eq!(quad.mesh.triangle_count(), 2);
+        assert_eq!((quad.mesh.positions.len(), quad.mesh.normals.len(), quad.mesh.uvs.len()), (4, 4, 4));
+        assert_eq!(quad.mesh.material.refraction_index, 1.5);
+        assert_eq!(quad.mesh.material.specular_exponent, 120.0);
+        assert_eq!(quad.mesh.material.emission().z, 0.5);
+        assert!((quad.mesh.material.transparency_factor - 0.75).abs() < 1e-6);
+
+        // The material carries over to the new group, which has no normals nor uvs
+        let wedge = &groups[1];
+        assert_eq!(wedge.name, "wedge");
+        assert_eq!(wedge.mesh.material.refraction_index, 1.5);
+        assert_eq!(wedge.mesh.triangle_count(), 1);
+        assert!(wedge.mesh.normals.is_empty() && wedge.mesh.uvs.is_empty());
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let path = write_files("errors", "v 0 0 0\nv 1 0 0\n\nf 1 2 3\n", "");
+        match load_obj(&path, &Material::white()) {
+            Err(ReadError::Format { line: Some(line), message, .. }) => {
+                assert_eq!(line, 4);
+                assert!(message.contains("position index 3"), "{}", message);
+            },
+            other => panic!("expected a parse error, got {:?}", other.map(|groups| groups.len()))
+        }
+
+        let path = write_files("bad-number", "v 0 zero 0\n", "");
+        assert!(matches!(load_obj(&path, &Material::white()), Err(ReadError::Format { line: Some(1), .. })));
+    }
+
+    #[test]
+    fn zero_normals_fall_back_to_the_face_normal() {
+        let path = write_files("zero-normals", "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 0\nf 1//1 2//1 3//1\n", "");
+        let groups = load_obj(&path, &Material::white()).unwrap();
+        let mesh = &groups[0].mesh;
+        assert_eq!(mesh.degenerate_normal_count(), 3);
+
+        let hit = mesh.ray_intersect(&Ray::new(Vec3::new(0.2, 0.2, 1.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f32::INFINITY).unwrap();
+        assert_eq!((hit.normal.x, hit.normal.y, hit.normal.z), (0.0, 0.0, 1.0));
+    }
+}
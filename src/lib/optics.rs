@@ -0,0 +1,220 @@
+use std::f32::consts::PI;
+
+use crate::vec3::Vec3;
+
+pub fn reflect(impident: &Vec3, normal: &Vec3) -> Vec3 {
+    impident - normal * 2.0 * Vec3::dot(impident, normal)
+}
+
+// Direction of the ray transmitted through the surface, `normal` points outside
+// the medium of index `refraction_index`. None on total internal reflection.
+pub fn refract(impident: &Vec3, normal: &Vec3, refraction_index: f32) -> Option<Vec3> {
+    let mut cosi = -Vec3::dot(impident, normal).clamp(-1.0, 1.0);
+    let mut etai = 1.0;
+    let mut etat = refraction_index;
+    let mut n = normal.clone();
+    if cosi < 0.0 {
+        // The ray is inside the medium and leaving it
+        cosi = -cosi;
+        n = -normal;
+        std::mem::swap(&mut etai, &mut etat);
+    }
+    let eta = etai / etat;
+    let k = 1.0 - eta * eta * (1.0 - cosi * cosi);
+    if k < 0.0 {
+        return None
+    }
+    Some(impident * eta + n * (eta * cosi - k.sqrt()))
+}
+
+// Fraction of the light reflected by a dielectric interface for unpolarized
+// light, `normal` points outside the medium of index `refraction_index`
+pub fn fresnel(impident: &Vec3, normal: &Vec3, refraction_index: f32) -> f32 {
+    let mut cosi = Vec3::dot(impident, normal).clamp(-1.0, 1.0);
+    let mut etai = 1.0;
+    let mut etat = refraction_index;
+    if cosi > 0.0 {
+        std::mem::swap(&mut etai, &mut etat);
+    }
+    let sint = etai / etat * (1.0 - cosi * cosi).max(0.0).sqrt();
+    if sint >= 1.0 {
+        // Total internal reflection
+        return 1.0
+    }
+    let cost = (1.0 - sint * sint).max(0.0).sqrt();
+    cosi = cosi.abs();
+    let rs = (etat * cosi - etai * cost) / (etat * cosi + etai * cost);
+    let rp = (etai * cosi - etat * cost) / (etai * cosi + etat * cost);
+    (rs * rs + rp * rp) / 2.0
+}
+
+// Schlick's approximation of `fresnel`
+pub fn schlick(impident: &Vec3, normal: &Vec3, refraction_index: f32) -> f32 {
+    let mut cosi = Vec3::dot(impident, normal).clamp(-1.0, 1.0);
+    let mut etai = 1.0;
+    let mut etat = refraction_index;
+    if cosi > 0.0 {
+        std::mem::swap(&mut etai, &mut etat);
+    }
+    let r0 = ((etai - etat) / (etai + etat)).powi(2);
+    if etai > etat {
+        // Leaving the denser medium, the angle that matters is the transmitted one
+        let sint = etai / etat * (1.0 - cosi * cosi).max(0.0).sqrt();
+        if sint >= 1.0 {
+            return 1.0
+        }
+        cosi = (1.0 - sint * sint).sqrt();
+    }
+    let c = 1.0 - cosi.abs();
+    r0 + (1.0 - r0) * c.powi(5)
+}
+
+// Moves the origin of a secondary ray off the surface, on the side it leaves
+// toward, so it does not hit the surface it starts from
+pub fn offset_origin(point: &Vec3, normal: &Vec3, direction: &Vec3) -> Vec3 {
+    if Vec3::dot(direction, normal) > 0.0 {
+        point + normal * 0.001
+    } else {
+        point - normal * 0.001
+    }
+}
+
+// Two unit vectors completing `normal` into an orthonormal basis (Duff et al. 2017)
+pub fn orthonormal_basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let sign = 1.0f32.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+    (
+        Vec3::new(1.0 + sign * normal.x * normal.x * a, sign * b, -sign * normal.x),
+        Vec3::new(b, sign + normal.y * normal.y * a, -normal.y)
+    )
+}
+
+// Direction on the hemisphere around `normal` with a pdf of cos(theta) / PI
+pub fn cosine_hemisphere(normal: &Vec3, u: (f32, f32)) -> Vec3 {
+    let r = u.0.sqrt();
+    let phi = 2.0 * PI * u.1;
+    let (tangent, bitangent) = orthonormal_basis(normal);
+
+    tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * (1.0 - u.0).max(0.0).sqrt()
+}
+
+// Direction on the hemisphere around `normal` with a pdf of 1 / (2 PI)
+pub fn uniform_hemisphere(normal: &Vec3, u: (f32, f32)) -> Vec3 {
+    let cos_theta = u.0;
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    let (tangent, bitangent) = orthonormal_basis(normal);
+
+    tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + normal * cos_theta
+}
+
+// Direction inside the cone around `axis` whose half angle has a cosine of
+// `cos_max`, with a pdf of 1 / (2 PI (1 - cos_max))
+pub fn uniform_cone(axis: &Vec3, cos_max: f32, u: (f32, f32)) -> Vec3 {
+    let cos_theta = 1.0 - u.0 * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    let (tangent, bitangent) = orthonormal_basis(axis);
+
+    tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + axis * cos_theta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ray::Ray,
+        sphere::Sphere,
+        material::Material,
+        hitable::Hitable
+    };
+
+    const GLASS_INDEX: f32 = 1.5;
+
+    fn sin_between(a: &Vec3, b: &Vec3) -> f32 {
+        Vec3::cross(&a.normalize(), &b.normalize()).lenght()
+    }
+
+    fn glass_sphere() -> Sphere {
+        let glass = Material::new(Vec3::new(1.0, 1.0, 1.0), 0.0, 1.0, 0.0, 0.0, GLASS_INDEX, 1.0);
+        Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, glass)
+    }
+
+    #[test]
+    fn refraction_entering_glass_follows_snell() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let impident = Vec3::new(0.5, -(0.75f32).sqrt(), 0.0);
+
+        let refracted = refract(&impident, &normal, GLASS_INDEX).unwrap();
+
+        assert!((refracted.lenght() - 1.0).abs() < 1e-5);
+        assert!(refracted.y < 0.0);
+        let sin_i = sin_between(&impident, &normal);
+        let sin_t = sin_between(&refracted, &normal);
+        assert!((sin_i - GLASS_INDEX * sin_t).abs() < 1e-5);
+    }
+
+    #[test]
+    fn refraction_leaving_glass_follows_snell() {
+        // Inside the medium, heading out through the outward facing normal
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let impident = Vec3::new(0.5, 0.75f32.sqrt(), 0.0);
+
+        let refracted = refract(&impident, &normal, GLASS_INDEX).unwrap();
+
+        assert!(refracted.y > 0.0);
+        let sin_i = sin_between(&impident, &normal);
+        let sin_t = sin_between(&refracted, &normal);
+        assert!((GLASS_INDEX * sin_i - sin_t).abs() < 1e-5);
+    }
+
+    #[test]
+    fn total_internal_reflection_past_the_critical_angle() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let critical = (1.0 / GLASS_INDEX).asin();
+
+        let below = critical - 0.01;
+        assert!(refract(&Vec3::new(below.sin(), below.cos(), 0.0), &normal, GLASS_INDEX).is_some());
+
+        let above = critical + 0.01;
+        assert!(refract(&Vec3::new(above.sin(), above.cos(), 0.0), &normal, GLASS_INDEX).is_none());
+        assert_eq!(fresnel(&Vec3::new(above.sin(), above.cos(), 0.0), &normal, GLASS_INDEX), 1.0);
+    }
+
+    #[test]
+    fn ray_through_glass_sphere_obeys_snell_at_both_interfaces() {
+        let sphere = glass_sphere();
+        let ray = Ray::new(Vec3::new(0.4, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let entry = sphere.ray_intersect(&ray, 0.0, f32::INFINITY).unwrap();
+        let inside_dir = refract(&ray.direction, &entry.normal, GLASS_INDEX).unwrap().normalize();
+        assert!((sin_between(&ray.direction, &entry.normal) - GLASS_INDEX * sin_between(&inside_dir, &entry.normal)).abs() < 1e-5);
+
+        // The refracted ray starts inside the sphere and must find the exit
+        let inside = Ray::new(offset_origin(&entry.hit_point, &entry.normal, &inside_dir), inside_dir.clone());
+        let exit = sphere.ray_intersect(&inside, 0.0, f32::INFINITY).unwrap();
+        assert!(exit.hit_distance > 0.0);
+        assert!(Vec3::dot(&inside_dir, &exit.normal) > 0.0);
+
+        let outside_dir = refract(&inside_dir, &exit.normal, GLASS_INDEX).unwrap().normalize();
+        assert!((GLASS_INDEX * sin_between(&inside_dir, &exit.normal) - sin_between(&outside_dir, &exit.normal)).abs() < 1e-5);
+
+        // A sphere is symmetric, the ray leaves at the angle it came in (up to
+        // the small shift of the secondary ray origin)
+        assert!((sin_between(&ray.direction, &entry.normal) - sin_between(&outside_dir, &exit.normal)).abs() < 1e-3);
+        // and a converging lens bends it toward the axis
+        assert!(outside_dir.x < 0.0);
+    }
+
+    #[test]
+    fn fresnel_is_stronger_at_grazing_angles() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let head_on = fresnel(&Vec3::new(0.0, -1.0, 0.0), &normal, GLASS_INDEX);
+        let grazing = fresnel(&Vec3::new(0.999, -(1.0f32 - 0.999 * 0.999).sqrt(), 0.0), &normal, GLASS_INDEX);
+
+        assert!((head_on - 0.04).abs() < 1e-3);
+        assert!(grazing > 0.5);
+        assert!((schlick(&Vec3::new(0.0, -1.0, 0.0), &normal, GLASS_INDEX) - 0.04).abs() < 1e-3);
+    }
+}
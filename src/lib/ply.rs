@@ -0,0 +1,517 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::{
+    vec3::Vec3,
+    material::Material,
+    mesh::TriangleMesh,
+    error::ReadError
+};
+
+// Loads the vertex and face elements of an ASCII or binary PLY file into a
+// mesh. Vertex normals and colors (red, green, blue) are kept when present,
+// faces with more than three vertices are triangulated as fans and every other
+// element is skipped. The file is streamed, only the mesh itself is kept.
+pub fn load_ply<P: AsRef<Path>>(path: P, material: Material) -> Result<TriangleMesh, ReadError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|error| ReadError::io(path, error))?;
+    let mut reader = BufReader::new(file);
+
+    let header = read_header(&mut reader).map_err(|error| error.at(path))?;
+    read_body(reader, &header, material).map_err(|error| error.at(path))
+}
+
+// Error before the path of the file is known
+enum ParseError {
+    Io(io::Error),
+    Format(String)
+}
+
+impl ParseError {
+    fn at(self, path: &Path) -> ReadError {
+        match self {
+            ParseError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                ReadError::format(path, String::from("the file ends before all the elements were read"))
+            },
+            ParseError::Io(error) => ReadError::io(path, error),
+            ParseError::Format(message) => ReadError::format(path, message)
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(error: io::Error) -> Self {
+        ParseError::Io(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8
+        }
+    }
+
+    // Colors stored as integers span the whole range of their type
+    fn color_scale(self) -> f32 {
+        match self {
+            Scalar::U8 => 1.0 / 255.0,
+            Scalar::U16 => 1.0 / 65535.0,
+            _ => 1.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    X,
+    Y,
+    Z,
+    NormalX,
+    NormalY,
+    NormalZ,
+    Red,
+    Green,
+    Blue,
+    U,
+    V,
+    Indices,
+    Ignored
+}
+
+#[derive(Debug)]
+struct Property {
+    scalar: Scalar,
+    // Type of the item count for list properties
+    list: Option<Scalar>,
+    role: Role
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>
+}
+
+impl Element {
+    fn has(&self, role: Role) -> bool {
+        self.properties.iter().any(|property| property.role == role)
+    }
+}
+
+#[derive(Debug)]
+struct Header {
+    encoding: Encoding,
+    elements: Vec<Element>
+}
+
+fn role(element: &str, property: &str) -> Role {
+    match (element, property) {
+        ("vertex", "x") => Role::X,
+        ("vertex", "y") => Role::Y,
+        ("vertex", "z") => Role::Z,
+        ("vertex", "nx") => Role::NormalX,
+        ("vertex", "ny") => Role::NormalY,
+        ("vertex", "nz") => Role::NormalZ,
+        ("vertex", "red") | ("vertex", "r") | ("vertex", "diffuse_red") => Role::Red,
+        ("vertex", "green") | ("vertex", "g") | ("vertex", "diffuse_green") => Role::Green,
+        ("vertex", "blue") | ("vertex", "b") | ("vertex", "diffuse_blue") => Role::Blue,
+        ("vertex", "u") | ("vertex", "s") | ("vertex", "texture_u") => Role::U,
+        ("vertex", "v") | ("vertex", "t") | ("vertex", "texture_v") => Role::V,
+        ("face", "vertex_indices") | ("face", "vertex_index") => Role::Indices,
+        _ => Role::Ignored
+    }
+}
+
+fn read_header<R: BufRead>(reader: &mut R) -> Result<Header, ParseError> {
+    let mut encoding = None;
+    let mut elements: Vec<Element> = vec![];
+    let mut line = String::new();
+    let mut number = 0;
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(ParseError::Format(String::from("the header has no `end_header`")))
+        }
+        number += 1;
+        let error = |message: String| Err(ParseError::Format(format!("header line {}: {}", number, message)));
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if number == 1 {
+            if tokens != ["ply"] {
+                return error(String::from("not a PLY file"))
+            }
+            continue;
+        }
+
+        match tokens.as_slice() {
+            ["format", format, _version] => {
+                encoding = Some(match *format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::LittleEndian,
+                    "binary_big_endian" => Encoding::BigEndian,
+                    _ => return error(format!("unknown format `{}`", format))
+                });
+            },
+            ["element", name, count] => {
+                let count = match count.parse() {
+                    Ok(count) => count,
+                    Err(_) => return error(format!("`{}` is not an element count", count))
+                };
+                elements.push(Element {
+                    name: name.to_string(),
+                    count,
+                    properties: vec![]
+                });
+            },
+            ["property", rest @ ..] => {
+                let element = match elements.last_mut() {
+                    Some(element) => element,
+                    None => return error(String::from("property before any element"))
+                };
+                let (scalar, list, name) = match rest {
+                    ["list", count, item, name] => (Scalar::parse(item), Some(Scalar::parse(count)), name),
+                    [scalar, name] => (Scalar::parse(scalar), None, name),
+                    _ => return error(format!("malformed property `{}`", line.trim()))
+                };
+                let (scalar, list) = match (scalar, list) {
+                    (Some(scalar), None) => (scalar, None),
+                    (Some(scalar), Some(Some(count))) => (scalar, Some(count)),
+                    _ => return error(format!("unknown type in `{}`", line.trim()))
+                };
+
+                let role = role(&element.name, name);
+                if (role == Role::Indices) != list.is_some() {
+                    return error(format!("unexpected property `{}`", line.trim()))
+                }
+                element.properties.push(Property {
+                    scalar,
+                    list,
+                    role
+                });
+            },
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {},
+            _ => return error(format!("unknown header statement `{}`", line.trim()))
+        }
+    }
+
+    let encoding = match encoding {
+        Some(encoding) => encoding,
+        None => return Err(ParseError::Format(String::from("the header has no `format`")))
+    };
+    match elements.iter().find(|element| element.name == "vertex") {
+        Some(vertex) if vertex.has(Role::X) && vertex.has(Role::Y) && vertex.has(Role::Z) => {},
+        _ => return Err(ParseError::Format(String::from("the file has no vertex positions")))
+    }
+    Ok(Header {
+        encoding,
+        elements
+    })
+}
+
+// Reads the scalars of the body one at a time, whatever their encoding
+struct ValueReader<R: BufRead> {
+    reader: R,
+    encoding: Encoding,
+    line: String,
+    line_number: usize,
+    // Byte offset of the next token in `line`
+    cursor: usize
+}
+
+impl<R: BufRead> ValueReader<R> {
+    fn next(&mut self, scalar: Scalar) -> Result<f64, ParseError> {
+        let mut bytes = [0u8; 8];
+        let bytes = &mut bytes[..scalar.size()];
+        match self.encoding {
+            Encoding::Ascii => return self.next_token(scalar),
+            Encoding::LittleEndian => self.reader.read_exact(bytes)?,
+            Encoding::BigEndian => {
+                self.reader.read_exact(bytes)?;
+                bytes.reverse();
+            }
+        }
+
+        // The bytes are now little endian
+        Ok(match scalar {
+            Scalar::I8 => bytes[0] as i8 as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+        })
+    }
+
+    fn next_token(&mut self, scalar: Scalar) -> Result<f64, ParseError> {
+        loop {
+            let rest = &self.line[self.cursor..];
+            let start = match rest.find(|c: char| !c.is_whitespace()) {
+                Some(start) => start,
+                None => {
+                    self.line.clear();
+                    self.cursor = 0;
+                    if self.reader.read_line(&mut self.line)? == 0 {
+                        return Err(ParseError::Io(io::ErrorKind::UnexpectedEof.into()))
+                    }
+                    self.line_number += 1;
+                    continue;
+                }
+            };
+            let rest = &rest[start..];
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let token = &rest[..end];
+            self.cursor += start + end;
+
+            let value = match scalar {
+                Scalar::F32 | Scalar::F64 => token.parse::<f64>().ok(),
+                _ => token.parse::<i64>().ok().map(|value| value as f64)
+            };
+            return value.ok_or_else(|| ParseError::Format(format!("line {}: `{}` is not a valid {:?}", self.line_number, token, scalar)))
+        }
+    }
+}
+
+const MAX_RESERVED_ELEMENTS: usize = 1 << 20;
+
+fn read_body<R: BufRead>(reader: R, header: &Header, material: Material) -> Result<TriangleMesh, ParseError> {
+    let mut values = ValueReader {
+        reader,
+        encoding: header.encoding,
+        line: String::new(),
+        line_number: 0,
+        cursor: 0
+    };
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut colors = vec![];
+    let mut indices = vec![];
+    let mut polygon = vec![];
+    // Every element is read in full or the file is rejected, so the header
+    // gives the number of vertices even before they are read
+    let vertex_count = header.elements.iter().filter(|element| element.name == "vertex")
+        .fold(0usize, |count, element| count.saturating_add(element.count));
+
+    for element in &header.elements {
+        let has_normals = element.has(Role::NormalX) && element.has(Role::NormalY) && element.has(Role::NormalZ);
+        let has_colors = element.has(Role::Red) && element.has(Role::Green) && element.has(Role::Blue);
+        // The counts come from the header, which may not match the rest of
+        // the file. Past the cap the vectors grow as the elements are read.
+        let reserved = element.count.min(MAX_RESERVED_ELEMENTS);
+        match element.name.as_str() {
+            "vertex" => {
+                positions.reserve_exact(reserved);
+                if has_normals {
+                    normals.reserve_exact(reserved);
+                }
+                if has_colors {
+                    colors.reserve_exact(reserved);
+                }
+            },
+            // Most scans are made of triangles
+            "face" => indices.reserve(reserved),
+            _ => {}
+        }
+
+        for index in 0..element.count {
+            let mut position = Vec3::new(0.0, 0.0, 0.0);
+            let mut normal = Vec3::new(0.0, 0.0, 0.0);
+            let mut color = Vec3::new(0.0, 0.0, 0.0);
+
+            for property in &element.properties {
+                if let Some(count_scalar) = property.list {
+                    let count = values.next(count_scalar)? as usize;
+                    polygon.clear();
+                    for _ in 0..count {
+                        polygon.push(values.next(property.scalar)?);
+                    }
+                    if property.role == Role::Indices {
+                        push_polygon(&polygon, vertex_count, &mut indices).map_err(|message| {
+                            ParseError::Format(format!("face {}: {}", index, message))
+                        })?;
+                    }
+                    continue;
+                }
+
+                let value = values.next(property.scalar)? as f32;
+                match property.role {
+                    Role::X => position.x = value,
+                    Role::Y => position.y = value,
+                    Role::Z => position.z = value,
+                    Role::NormalX => normal.x = value,
+                    Role::NormalY => normal.y = value,
+                    Role::NormalZ => normal.z = value,
+                    Role::Red => color.x = value * property.scalar.color_scale(),
+                    Role::Green => color.y = value * property.scalar.color_scale(),
+                    Role::Blue => color.z = value * property.scalar.color_scale(),
+                    // Texture coordinates are not used for shading yet
+                    Role::U | Role::V | Role::Indices | Role::Ignored => {}
+                }
+            }
+
+            if element.name == "vertex" {
+                positions.push(position);
+                if has_normals {
+                    normals.push(normal);
+                }
+                if has_colors {
+                    colors.push(color);
+                }
+            }
+        }
+    }
+
+    let mut mesh = TriangleMesh::new(positions, indices, material).map_err(|error| ParseError::Format(error.to_string()))?;
+    // Several vertex elements may not all have normals or colors
+    if !normals.is_empty() {
+        mesh.set_normals(normals).map_err(|error| ParseError::Format(error.to_string()))?;
+    }
+    if !colors.is_empty() {
+        mesh.set_colors(colors).map_err(|error| ParseError::Format(error.to_string()))?;
+    }
+    Ok(mesh)
+}
+
+fn push_polygon(polygon: &[f64], vertex_count: usize, indices: &mut Vec<[u32; 3]>) -> Result<(), String> {
+    if polygon.iter().any(|&index| index < 0.0 || index > u32::MAX as f64) {
+        return Err(String::from("negative or too large vertex index"))
+    }
+    if let Some(&index) = polygon.iter().find(|&&index| index as usize >= vertex_count) {
+        return Err(format!("vertex {} is out of the {} defined", index, vertex_count))
+    }
+    // Points and edges have nothing to render
+    for i in 1..polygon.len().saturating_sub(1) {
+        indices.push([polygon[0] as u32, polygon[i] as u32, polygon[i + 1] as u32]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn write_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("tiny-raytracer-{}.ply", name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    // A colored quad followed by an element the reader has to skip
+    fn binary_quad(little_endian: bool) -> Vec<u8> {
+        let format = if little_endian { "binary_little_endian" } else { "binary_big_endian" };
+        let mut contents = format!("ply\nformat {} 1.0\ncomment quad\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\n\
+            element edge 1\nproperty int vertex1\nproperty int vertex2\nend_header\n", format).into_bytes();
+        let word = |bytes: [u8; 4]| if little_endian { bytes } else { [bytes[3], bytes[2], bytes[1], bytes[0]] };
+
+        for &(x, y, red) in &[(0.0f32, 0.0f32, 255u8), (1.0, 0.0, 0), (1.0, 1.0, 0), (0.0, 1.0, 51)] {
+            for coordinate in &[x, y, 0.0] {
+                contents.extend_from_slice(&word(coordinate.to_le_bytes()));
+            }
+            contents.extend_from_slice(&[red, 0, 0]);
+        }
+        contents.push(4);
+        for index in &[0i32, 1, 2, 3, 0, 1] {
+            contents.extend_from_slice(&word(index.to_le_bytes()));
+        }
+        contents
+    }
+
+    #[test]
+    fn ascii_and_binary_files_give_the_same_mesh() {
+        let ascii = write_file("ascii", b"ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\n\
+            element edge 1\nproperty int vertex1\nproperty int vertex2\nend_header\n\
+            0 0 0 255 0 0\n1 0 0 0 0 0\n1 1 0 0 0 0\n0 1 0 51 0 0\n4 0 1 2 3\n0 1\n");
+        let little = write_file("little", &binary_quad(true));
+        let big = write_file("big", &binary_quad(false));
+
+        for path in &[ascii, little, big] {
+            let mesh = load_ply(path, Material::white()).unwrap();
+            assert_eq!(mesh.triangle_count(), 2, "{}", path.display());
+            assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3]]);
+            assert_eq!(mesh.positions[2].x, 1.0);
+            assert_eq!(mesh.positions[2].y, 1.0);
+            assert!(mesh.normals.is_empty());
+            assert_eq!(mesh.colors[0].x, 1.0);
+            assert!((mesh.colors[3].x - 0.2).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn truncated_and_broken_files_are_errors() {
+        let truncated = write_file("truncated", b"ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n");
+        assert!(matches!(load_ply(&truncated, Material::white()), Err(ReadError::Format { .. })));
+
+        let out_of_range = write_file("out-of-range", b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar uint vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n");
+        match load_ply(&out_of_range, Material::white()) {
+            Err(ReadError::Format { message, .. }) => assert!(message.contains("face 0"), "{}", message),
+            other => panic!("expected a format error, got {:?}", other.map(|mesh| mesh.triangle_count()))
+        }
+
+        // The second face is a quad, the error names it and not its triangles
+        let quad = write_file("out-of-range-quad", b"ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 2\nproperty list uchar uint vertex_indices\nend_header\n0 0 0\n1 0 0\n1 1 0\n0 1 0\n3 0 1 2\n4 0 1 2 7\n");
+        match load_ply(&quad, Material::white()) {
+            Err(ReadError::Format { message, .. }) => assert!(message.contains("face 1: vertex 7 is out of the 4 defined"), "{}", message),
+            other => panic!("expected a format error, got {:?}", other.map(|mesh| mesh.triangle_count()))
+        }
+    }
+
+    #[test]
+    fn lying_headers_are_errors() {
+        let lying = write_file("lying", b"ply\nformat binary_little_endian 1.0\nelement vertex 4000000000000\nproperty float x\nproperty float y\n\
+            property float z\nproperty float nx\nproperty float ny\nproperty float nz\nelement face 4000000000000\n\
+            property list uchar int vertex_indices\nend_header\n\0\0\0\0");
+        match load_ply(&lying, Material::white()) {
+            Err(ReadError::Format { message, .. }) => assert!(message.contains("ends before"), "{}", message),
+            other => panic!("expected a format error, got {:?}", other.map(|mesh| mesh.triangle_count()))
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub completed_tiles: usize,
+    pub total_tiles: usize,
+    pub completed_pixels: usize,
+    pub total_pixels: usize,
+    pub elapsed: Duration
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f32 {
+        if self.total_pixels == 0 {
+            return 1.0
+        }
+        self.completed_pixels as f32 / self.total_pixels as f32
+    }
+
+    // Extrapolated from the pixel throughput so far, None until something is done
+    pub fn eta(&self) -> Option<Duration> {
+        if self.completed_pixels == 0 {
+            return None
+        }
+        let remaining = (self.total_pixels - self.completed_pixels) as f64;
+        let per_pixel = self.elapsed.as_secs_f64() / self.completed_pixels as f64;
+        Some(Duration::from_secs_f64(remaining * per_pixel))
+    }
+}
+
+pub trait RenderObserver: Sync + Send {
+    fn on_progress(&self, progress: &Progress);
+
+    fn on_finish(&self, _progress: &Progress, _cancelled: bool) {}
+}
+
+// Prints the number of remaining tiles every `every` tiles
+pub struct ConsoleProgress {
+    every: usize
+}
+
+impl ConsoleProgress {
+    pub fn new(every: usize) -> ConsoleProgress {
+        ConsoleProgress {
+            every: every.max(1)
+        }
+    }
+}
+
+impl RenderObserver for ConsoleProgress {
+    fn on_progress(&self, progress: &Progress) {
+        let remaining = progress.total_tiles - progress.completed_tiles;
+        if !remaining.is_multiple_of(self.every) {
+            return
+        }
+        match progress.eta() {
+            Some(eta) => println!("{} tiles remaining, {:.1}s left", remaining, eta.as_secs_f32()),
+            None => println!("{} tiles remaining", remaining)
+        }
+    }
+
+    fn on_finish(&self, progress: &Progress, cancelled: bool) {
+        if cancelled {
+            println!("Cancelled with {} of {} tiles done", progress.completed_tiles, progress.total_tiles);
+        }
+    }
+}
+
+// Shared flag used to stop a render from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(completed_pixels: usize, total_pixels: usize, elapsed: f32) -> Progress {
+        Progress {
+            completed_tiles: 0,
+            total_tiles: 0,
+            completed_pixels,
+            total_pixels,
+            elapsed: Duration::from_secs_f32(elapsed)
+        }
+    }
+
+    #[test]
+    fn fraction_is_the_share_of_pixels_done() {
+        assert_eq!(progress(0, 100, 0.0).fraction(), 0.0);
+        assert_eq!(progress(25, 100, 1.0).fraction(), 0.25);
+        assert_eq!(progress(100, 100, 4.0).fraction(), 1.0);
+        assert_eq!(progress(0, 0, 0.0).fraction(), 1.0);
+    }
+
+    #[test]
+    fn eta_extrapolates_the_pixel_throughput() {
+        assert_eq!(progress(0, 100, 1.0).eta(), None);
+        assert_eq!(progress(0, 0, 0.0).eta(), None);
+        assert_eq!(progress(25, 100, 1.0).eta(), Some(Duration::from_secs(3)));
+        assert_eq!(progress(100, 100, 4.0).eta(), Some(Duration::from_secs(0)));
+    }
+}
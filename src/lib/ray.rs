@@ -1,4 +1,4 @@
-use crate::lib::vec3::Vec3;
+use crate::vec3::Vec3;
 
 #[derive(Debug, Clone)]
 pub struct Ray {
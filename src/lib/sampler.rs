@@ -0,0 +1,372 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131
+];
+
+// Produces the random numbers of every sample of a pixel. Each call to next_1d
+// or next_2d consumes the next dimension of the current sample: the camera
+// jitter uses the first two, lens, light and BSDF sampling take the following ones.
+pub trait Sampler: Send {
+    fn start_pixel(&mut self, seed: u64);
+
+    fn start_sample(&mut self, index: usize);
+
+    fn next_1d(&mut self) -> f32;
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    R2
+}
+
+impl SamplerKind {
+    pub fn create(&self, samples_per_pixel: usize) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new()),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(samples_per_pixel)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new()),
+            SamplerKind::R2 => Box::new(R2Sampler::new(samples_per_pixel))
+        }
+    }
+}
+
+// SplitMix64 finalizer, used to derive independent seeds from a seed and an index
+pub fn hash(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Random value in [0, 1) fixed for a pixel and a dimension
+fn dimension_offset(seed: u64, dimension: usize) -> f32 {
+    (hash(seed ^ hash(dimension as u64)) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// Seeded random permutation of [0, count) evaluated one element at a time
+// (Kensler 2013, "Correlated Multi-Jittered Sampling")
+fn permute(mut index: u32, count: u32, seed: u32) -> u32 {
+    let mut mask = count.wrapping_sub(1);
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+    // Permutes [0, mask] until the result lands in [0, count)
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170_893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929_eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935_fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dc_b303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e50_1cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860_a3df);
+        index &= mask;
+        index ^= index >> 5;
+        if index < count {
+            break;
+        }
+    }
+    (index.wrapping_add(seed)) % count
+}
+
+// Shuffles the sample indices of a pixel for one dimension. Indices past
+// `count` are shuffled by blocks of `count`.
+fn shuffled_index(index: usize, count: usize, seed: u64, dimension: usize) -> usize {
+    let block = index / count;
+    let block_seed = hash(seed ^ hash(dimension as u64) ^ hash(block as u64).rotate_left(17)) as u32;
+    block * count + permute((index % count) as u32, count as u32, block_seed) as usize
+}
+
+fn wrap(value: f32) -> f32 {
+    value.fract().min(ONE_MINUS_EPSILON)
+}
+
+pub struct IndependentSampler {
+    rng: StdRng
+}
+
+impl IndependentSampler {
+    pub fn new() -> IndependentSampler {
+        IndependentSampler {
+            rng: StdRng::seed_from_u64(0)
+        }
+    }
+}
+
+impl Default for IndependentSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed)
+    }
+
+    fn start_sample(&mut self, _index: usize) {}
+
+    fn next_1d(&mut self) -> f32 {
+        self.rng.gen()
+    }
+}
+
+// Jittered sampling: every dimension is cut into one stratum per sample (a
+// square grid for 2D requests) and each sample of the pixel falls in its own
+// stratum. Each dimension visits its strata in its own random order, so the
+// dimensions stay uncorrelated.
+pub struct StratifiedSampler {
+    samples_per_pixel: usize,
+    grid_size: usize,
+    seed: u64,
+    index: usize,
+    dimension: usize,
+    rng: StdRng
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize) -> StratifiedSampler {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        StratifiedSampler {
+            samples_per_pixel,
+            grid_size: (samples_per_pixel as f32).sqrt().ceil() as usize,
+            seed: 0,
+            index: 0,
+            dimension: 0,
+            rng: StdRng::seed_from_u64(0)
+        }
+    }
+
+    fn stratum(&mut self, count: usize) -> usize {
+        let stratum = shuffled_index(self.index, count, self.seed, self.dimension);
+        self.dimension += 1;
+        stratum % count
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed)
+    }
+
+    fn start_sample(&mut self, index: usize) {
+        self.index = index;
+        self.dimension = 0
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let stratum = self.stratum(self.samples_per_pixel);
+        let jitter: f32 = self.rng.gen();
+        ((stratum as f32 + jitter) / self.samples_per_pixel as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let size = self.grid_size;
+        let stratum = self.stratum(size * size);
+        self.dimension += 1;
+        let (jitter_x, jitter_y): (f32, f32) = (self.rng.gen(), self.rng.gen());
+        (
+            (((stratum % size) as f32 + jitter_x) / size as f32).min(ONE_MINUS_EPSILON),
+            (((stratum / size) as f32 + jitter_y) / size as f32).min(ONE_MINUS_EPSILON)
+        )
+    }
+}
+
+// Halton sequence with one prime base per dimension. Every pixel walks the same
+// sequence shifted by a random offset per dimension (Cranley-Patterson rotation).
+pub struct HaltonSampler {
+    seed: u64,
+    index: usize,
+    dimension: usize,
+    rng: StdRng
+}
+
+impl HaltonSampler {
+    pub fn new() -> HaltonSampler {
+        HaltonSampler {
+            seed: 0,
+            index: 0,
+            dimension: 0,
+            rng: StdRng::seed_from_u64(0)
+        }
+    }
+
+    fn radical_inverse(base: u32, mut index: u64) -> f32 {
+        let inv_base = 1.0 / base as f64;
+        let mut inv_base_n = 1.0;
+        let mut reversed = 0.0;
+        while index > 0 {
+            let digit = index % base as u64;
+            inv_base_n *= inv_base;
+            reversed += digit as f64 * inv_base_n;
+            index /= base as u64;
+        }
+        (reversed as f32).min(ONE_MINUS_EPSILON)
+    }
+}
+
+impl Default for HaltonSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed)
+    }
+
+    fn start_sample(&mut self, index: usize) {
+        self.index = index;
+        self.dimension = 0
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        if dimension >= PRIMES.len() {
+            // The high bases correlate badly, fall back to plain random numbers
+            return self.rng.gen()
+        }
+        let value = Self::radical_inverse(PRIMES[dimension], self.index as u64 + 1);
+        wrap(value + dimension_offset(self.seed, dimension))
+    }
+}
+
+// Martin Roberts' R2 sequence: additive recurrence on the plastic number for 2D
+// requests and on the golden ratio for 1D ones. Each dimension walks the
+// points in its own random order (over blocks of samples_per_pixel points)
+// and is shifted by its own random offset (Cranley-Patterson rotation), so
+// the dimensions stay uncorrelated.
+pub struct R2Sampler {
+    samples_per_pixel: usize,
+    seed: u64,
+    index: usize,
+    dimension: usize
+}
+
+impl R2Sampler {
+    const GOLDEN: f64 = 1.618_033_988_749_895;
+    const PLASTIC: f64 = 1.324_717_957_244_746;
+
+    pub fn new(samples_per_pixel: usize) -> R2Sampler {
+        R2Sampler {
+            samples_per_pixel: samples_per_pixel.max(1),
+            seed: 0,
+            index: 0,
+            dimension: 0
+        }
+    }
+
+    fn recurrence(&self, alpha: f64, index: usize, offset: f32) -> f32 {
+        let value = (0.5 + alpha * (index as f64 + 1.0)).fract() as f32;
+        wrap(value + offset)
+    }
+}
+
+impl Sampler for R2Sampler {
+    fn start_pixel(&mut self, seed: u64) {
+        self.seed = seed
+    }
+
+    fn start_sample(&mut self, index: usize) {
+        self.index = index;
+        self.dimension = 0
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let index = shuffled_index(self.index, self.samples_per_pixel, self.seed, self.dimension);
+        let offset = dimension_offset(self.seed, self.dimension);
+        self.dimension += 1;
+        self.recurrence(1.0 / Self::GOLDEN, index, offset)
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        // Both coordinates share the point, only the pair is shuffled
+        let index = shuffled_index(self.index, self.samples_per_pixel, self.seed, self.dimension);
+        let offset_x = dimension_offset(self.seed, self.dimension);
+        let offset_y = dimension_offset(self.seed, self.dimension + 1);
+        self.dimension += 2;
+        (
+            self.recurrence(1.0 / Self::PLASTIC, index, offset_x),
+            self.recurrence(1.0 / (Self::PLASTIC * Self::PLASTIC), index, offset_y)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn correlation(pairs: &[(f32, f32)]) -> f32 {
+        let n = pairs.len() as f32;
+        let (mean_a, mean_b) = pairs.iter().fold((0.0, 0.0), |(a, b), (x, y)| (a + x / n, b + y / n));
+        let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+        for (a, b) in pairs {
+            covariance += (a - mean_a) * (b - mean_b);
+            variance_a += (a - mean_a) * (a - mean_a);
+            variance_b += (b - mean_b) * (b - mean_b);
+        }
+        covariance / (variance_a * variance_b).sqrt()
+    }
+
+    #[test]
+    fn permutations_are_bijections() {
+        for count in [1, 2, 7, 64, 100] {
+            let mut seen = vec![false; count as usize];
+            for index in 0..count {
+                seen[permute(index, count, 0x1234_5678) as usize] = true;
+            }
+            assert!(seen.iter().all(|&seen| seen), "{}", count);
+        }
+    }
+
+    #[test]
+    fn dimensions_are_uncorrelated() {
+        let samples = 64;
+        for kind in [SamplerKind::Independent, SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::R2] {
+            let mut sampler = kind.create(samples);
+            // Consecutive 1D dimensions, and two consecutive 2D requests
+            let (mut one, mut two) = (vec![], vec![]);
+            for pixel in 0..16 {
+                sampler.start_pixel(hash(pixel));
+                for index in 0..samples {
+                    sampler.start_sample(index);
+                    let (first, second) = (sampler.next_1d(), sampler.next_1d());
+                    let (a, _) = sampler.next_2d();
+                    let (b, _) = sampler.next_2d();
+                    one.push((first, second));
+                    two.push((a, b));
+                }
+            }
+            for pairs in [&one, &two] {
+                let correlation = correlation(pairs);
+                assert!(correlation.abs() < 0.1, "{:?}: {}", kind, correlation);
+            }
+        }
+    }
+}
@@ -1,22 +1,47 @@
-use std::path::Path;
-use std::fs::File;
-use std::io::BufWriter;
+use std::sync::mpsc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::sync::OnceLock;
+use std::time::Instant;
 
-use png;
-
-use crate::lib::{
+use crate::{
     vec3::Vec3,
     ray::Ray,
-    light::Light,
+    light::{Light, LightSample},
+    background::Environment,
+    integrator::{Integrator, WhittedIntegrator},
+    optics,
+    bvh::Bvh,
     hitable::{Hitable, HitInfos},
-    camera::Camera
+    camera::Camera,
+    tile::Tile,
+    framebuffer::Framebuffer,
+    progress::{Progress, RenderObserver, CancelToken},
+    error::RenderError,
+    sampler::{self, Sampler, SamplerKind},
+    adaptive::{AdaptiveSampling, PixelStats}
 };
 
+const DEFAULT_TILE_SIZE: usize = 32;
+
 pub struct Scene {
     rays_per_pixel: usize,
     max_recurtion: usize,
+    threads: usize,
+    tile_size: usize,
+    seed: u64,
+    sampler: SamplerKind,
+    adaptive: Option<AdaptiveSampling>,
+    integrator: Box<dyn Integrator>,
     objects: Vec<Box<dyn Hitable + Sync>>,
-    lights: Vec<Light>,
+    // Whether the emission of each object is sampled by one of the lights
+    sampled_emission: Vec<bool>,
+    // Why an emitting object could not be sampled, reported by render
+    emission_error: Option<String>,
+    bvh: OnceLock<Bvh>,
+    lights: Vec<Box<dyn Light>>,
+    environment: Environment,
+    observers: Vec<Box<dyn RenderObserver>>,
     camera: Camera
 }
 
@@ -25,173 +50,430 @@ impl Scene {
         Scene {
             rays_per_pixel,
             max_recurtion,
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            tile_size: DEFAULT_TILE_SIZE,
+            seed: 0,
+            sampler: SamplerKind::Independent,
+            adaptive: None,
+            integrator: Box::new(WhittedIntegrator),
             objects: vec![],
+            sampled_emission: vec![],
+            emission_error: None,
+            bvh: OnceLock::new(),
             lights: vec![],
+            environment: Environment::default(),
+            observers: vec![],
             camera
         }
     }
 
+    // Emitting objects are also added as lights sampling their emission
     pub fn push_object<T: Hitable + 'static>(&mut self, object: T) {
-        self.objects.push(Box::new(object))
+        let sampled = match object.emitter() {
+            Ok(Some(light)) => {
+                self.lights.push(light);
+                true
+            },
+            Ok(None) => false,
+            Err(message) => {
+                self.emission_error.get_or_insert(message);
+                false
+            }
+        };
+        self.objects.push(Box::new(object));
+        self.sampled_emission.push(sampled);
+        // Rebuilt on the next render or intersection
+        self.bvh = OnceLock::new();
     }
 
-    pub fn push_light(&mut self, light: Light) {
-        self.lights.push(light)
+    pub fn push_light<T: Light + 'static>(&mut self, light: T) {
+        self.lights.push(Box::new(light))
     }
 
-    pub fn render(&self, path: &str) {        
-        let mut data = vec![];
-        for j in 0..self.camera.height {
-            if (self.camera.height - j) % 5 == 0 {
-                println!("{} rows remaining", self.camera.height - j);
-            }
-            
-            for i in 0..self.camera.width {
-                let mut color = Vec3::new(0.0, 0.0, 0.0);
-                
-                for _ in 0..self.rays_per_pixel {
-                    let ray = self.camera.compute_camera(i, j);
-                    color = color + self.cast_ray(&ray, 0);
+    pub fn push_observer<T: RenderObserver + 'static>(&mut self, observer: T) {
+        self.observers.push(Box::new(observer))
+    }
+
+    pub fn set_rays_per_pixel(&mut self, rays_per_pixel: usize) {
+        self.rays_per_pixel = rays_per_pixel.max(1)
+    }
+
+    pub fn set_max_recurtion(&mut self, max_recurtion: usize) {
+        self.max_recurtion = max_recurtion
+    }
+
+    pub fn set_resolution(&mut self, width: usize, height: usize) {
+        self.camera.set_resolution(width.max(1), height.max(1))
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1)
+    }
+
+    pub fn set_tile_size(&mut self, tile_size: usize) {
+        self.tile_size = tile_size.max(1)
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed
+    }
+
+    pub fn set_sampler(&mut self, sampler: SamplerKind) {
+        self.sampler = sampler
+    }
+
+    // Replaces the fixed rays_per_pixel with per pixel adaptive sampling
+    pub fn set_adaptive_sampling(&mut self, adaptive: Option<AdaptiveSampling>) {
+        self.adaptive = adaptive
+    }
+
+    pub fn set_integrator<T: Integrator + 'static>(&mut self, integrator: T) {
+        self.integrator = Box::new(integrator)
+    }
+
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.environment = environment;
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn max_recurtion(&self) -> usize {
+        self.max_recurtion
+    }
+
+    pub fn lights(&self) -> &[Box<dyn Light>] {
+        &self.lights
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    // Radiance of the environment for a ray leaving the scene
+    pub fn background(&self, direction: &Vec3) -> Vec3 {
+        self.environment.radiance(&direction.normalize())
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> Option<HitInfos<'_>> {
+        self.intersect_sampled(ray).map(|(hit, _)| hit)
+    }
+
+    // Closest hit, and whether the emission of the object hit is sampled by
+    // one of the lights
+    pub fn intersect_sampled(&self, ray: &Ray) -> Option<(HitInfos<'_>, bool)> {
+        let mut closest: Option<(HitInfos, bool)> = None;
+        self.bvh().traverse(ray, f32::INFINITY, |index, t_max| {
+            let hit = self.objects[index].ray_intersect(ray, 0.0, t_max)?;
+            let distance = hit.hit_distance;
+            closest = Some((hit, self.sampled_emission[index]));
+            Some(distance)
+        });
+        closest
+    }
+
+    // Radiance of the closest light whose shape the ray hits before `t_max`
+    pub fn light_emission(&self, ray: &Ray, t_max: f32) -> Option<Vec3> {
+        let mut closest = None;
+        let mut closest_distance = t_max;
+        for light in &self.lights {
+            if let Some((distance, radiance)) = light.intersect(ray) {
+                if distance < closest_distance {
+                    closest_distance = distance;
+                    closest = Some(radiance);
                 }
-                let c = self.get_color(color);
-                
-                data.push(c[0]);
-                data.push(c[1]);
-                data.push(c[2]);    
-            } 
+            }
         }
-        self.save_as_png(path, data);
+        closest
+    }
+
+    // Whether anything lies on the ray before `t_max`
+    pub fn occluded(&self, ray: &Ray, t_max: f32) -> bool {
+        self.bvh().any_hit(ray, t_max, |index, t_max| self.objects[index].occluded(ray, 0.0, t_max))
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let bounds: Vec<_> = self.objects.iter().map(|object| object.bounding_box()).collect();
+            Bvh::build(&bounds)
+        })
     }
 
-    fn cast_ray(&self, ray: &Ray, recurtion: usize) -> Vec3 { 
-        let mut infos = vec![];
-        let _: Vec<_> = self.objects.iter().map(|sphere| infos.push(sphere.ray_intersect(&ray))).collect();
-        let closest = HitInfos::get_closest(infos);
-        
-        if recurtion > self.max_recurtion || closest.is_none() {
-            return Vec3::new(0.2, 0.7, 0.9)
+    pub fn is_shadowed(&self, hit: &HitInfos, light: &LightSample) -> bool {
+        let shadow_origin = optics::offset_origin(&hit.hit_point, &hit.normal, &light.direction);
+        if light.distance.is_infinite() {
+            return self.occluded(&Ray::new(shadow_origin, light.direction.clone()), f32::INFINITY)
         }
 
-        let closest = closest.unwrap();
-        let material = closest.material;
+        // Aimed from the moved origin at the sampled point, stopping short of
+        // it: the surface of the light may be an object of the scene
+        let to_light = &hit.hit_point + &light.direction * light.distance - &shadow_origin;
+        let distance = to_light.lenght();
+        self.occluded(&Ray::new(shadow_origin, to_light / distance), distance - 0.001)
+    }
 
-        let mut diffuse_light_intensity = 0.0;
-        let mut specular_light_intensity = 0.0;
-    
-        for light in &self.lights {
-            let light_dir = (&light.position - &closest.hit_point).normalize();
-            let light_dist = (&light.position - &closest.hit_point).lenght();
-            
-            let shadow_origin = if Vec3::dot(&light_dir, &closest.normal) > 0.0 {
-                &closest.hit_point + &closest.normal * 0.001
-            } else {
-                &closest.hit_point - &closest.normal * 0.001
+    pub fn render(&self) -> Result<Framebuffer, RenderError> {
+        self.render_cancellable(&CancelToken::new())
+    }
+
+    // Stops handing out tiles once the token is cancelled, the tiles that were
+    // not rendered are left black in the returned framebuffer
+    pub fn render_cancellable(&self, cancel: &CancelToken) -> Result<Framebuffer, RenderError> {
+        self.check_settings()?;
+        let start = Instant::now();
+        // Built before the workers start so they do not all wait on it
+        self.bvh();
+        let width = self.camera.width;
+        let tiles = Tile::split(width, self.camera.height, self.tile_size);
+        let next_tile = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+
+        let mut framebuffer = Framebuffer::new(width, self.camera.height);
+        let result = thread::scope(|s| {
+            let mut workers = vec![];
+            for _ in 0..self.threads.min(tiles.len()) {
+                let sender = sender.clone();
+                let tiles = &tiles;
+                let next_tile = &next_tile;
+                workers.push(s.spawn(move || loop {
+                    let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                    if index >= tiles.len() {
+                        break;
+                    }
+                    let pixels = match self.render_tile(&tiles[index], cancel) {
+                        Some(pixels) => pixels,
+                        None => break
+                    };
+                    if sender.send((index, pixels)).is_err() {
+                        break;
+                    }
+                }));
+            }
+            drop(sender);
+
+            let mut progress = Progress {
+                completed_tiles: 0,
+                total_tiles: tiles.len(),
+                completed_pixels: 0,
+                total_pixels: width * self.camera.height,
+                elapsed: start.elapsed()
             };
-    
-            let shadow_ray = Ray::new(shadow_origin.clone(), light_dir.clone());
-            let mut shadows_infos = vec![];
-            let _: Vec<_> = self.objects.iter().map(|sphere| shadows_infos.push(sphere.ray_intersect(&shadow_ray))).collect();
-    
-            let closest_shadow = HitInfos::get_closest(shadows_infos);
-            if closest_shadow.is_some() && (closest_shadow.unwrap().hit_point - shadow_origin).lenght() < light_dist {
-                continue;
+
+            // Tiles come back in whatever order the workers finish them, each one
+            // is copied to its own place so the image does not depend on scheduling
+            for (index, pixels) in receiver.iter() {
+                let tile = &tiles[index];
+                for (n, stats) in pixels.into_iter().enumerate() {
+                    let (x, y) = (tile.x + n % tile.width, tile.y + n / tile.width);
+                    framebuffer.set(x, y, stats.average());
+                    framebuffer.set_sample_count(x, y, stats.count as u32);
+                }
+
+                progress.completed_tiles += 1;
+                progress.completed_pixels += tile.pixel_count();
+                progress.elapsed = start.elapsed();
+                for observer in &self.observers {
+                    observer.on_progress(&progress);
+                }
+            }
+
+            for observer in &self.observers {
+                observer.on_finish(&progress, cancel.is_cancelled());
             }
-    
-            
-            let dot = Vec3::dot(&light_dir, &closest.normal);
-            diffuse_light_intensity += light.intensity * dot.max(0.0);
-    
-            
-            let specular_reflect_dir = Self::reflect(&light_dir, &closest.normal);
-            specular_light_intensity += (Vec3::dot(&specular_reflect_dir, &ray.direction)).max(0.0).powf(material.specular_exponent)  * light.intensity;   
+
+            // Joined here so a panic in a worker is returned instead of
+            // propagated by the scope
+            for worker in workers {
+                if let Err(payload) = worker.join() {
+                    let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| String::from("unknown panic"));
+                    return Err(RenderError::Worker(message))
+                }
+            }
+            Ok(())
+        });
+        result.map(|_| framebuffer)
+    }
+
+    fn check_settings(&self) -> Result<(), RenderError> {
+        if self.camera.width == 0 || self.camera.height == 0 {
+            return Err(RenderError::InvalidSettings(format!("the image is {}x{} pixels", self.camera.width, self.camera.height)))
         }
-        
-        let reflect_dir = Self::reflect(&ray.direction, &closest.normal);
-    
-        let reflect_origin = if Vec3::dot(&reflect_dir, &closest.normal) > 0.0 {
-            &closest.hit_point + &closest.normal * 0.001
-        } else {
-            &closest.hit_point - &closest.normal * 0.001
-        };
-    
-        let reflect_ray = Ray::new(reflect_origin, reflect_dir);
-        let reflect_color = self.cast_ray(&reflect_ray, recurtion + 1);
-    
-
-        let refract_dir = Self::refract(&ray.direction, &closest.normal, material.refraction_index).normalize();
-    
-        let refract_origin = if Vec3::dot(&refract_dir, &closest.normal) > 0.0 {
-            &closest.hit_point + &closest.normal * 0.001
-        } else {
-            &closest.hit_point - &closest.normal * 0.001
+        if let Some(message) = &self.emission_error {
+            return Err(RenderError::InvalidSettings(message.clone()))
+        }
+        match &self.adaptive {
+            Some(adaptive) if adaptive.min_samples == 0 || adaptive.min_samples > adaptive.max_samples => {
+                Err(RenderError::InvalidSettings(format!(
+                    "adaptive sampling needs 0 < min_samples <= max_samples, got {} and {}",
+                    adaptive.min_samples, adaptive.max_samples
+                )))
+            },
+            Some(adaptive) if adaptive.batch_size == 0 => {
+                Err(RenderError::InvalidSettings(String::from("adaptive sampling needs a batch_size of at least 1")))
+            },
+            None if self.rays_per_pixel == 0 => Err(RenderError::InvalidSettings(String::from("rays_per_pixel is 0"))),
+            _ => Ok(())
+        }
+    }
+
+    // Checked once per row, a cancelled tile is dropped as a whole so the
+    // framebuffer never holds half rendered tiles
+    fn render_tile(&self, tile: &Tile, cancel: &CancelToken) -> Option<Vec<PixelStats>> {
+        let mut pixels = Vec::with_capacity(tile.pixel_count());
+        let max_samples = match &self.adaptive {
+            Some(adaptive) => adaptive.max_samples,
+            None => self.rays_per_pixel
         };
-    
-        let refract_ray = Ray::new(refract_origin, refract_dir);
-        let refract_color = self.cast_ray(&refract_ray, recurtion + 1);
-        
+        let mut sampler = self.sampler.create(max_samples);
 
-        let mut color = material.color;
+        for j in tile.y..tile.y + tile.height {
+            if cancel.is_cancelled() {
+                return None
+            }
+            for i in tile.x..tile.x + tile.width {
+                let mut stats = PixelStats::new();
+                sampler.start_pixel(self.pixel_seed(i, j));
 
-        color = color * diffuse_light_intensity * material.color_albedo;
+                match &self.adaptive {
+                    Some(adaptive) => {
+                        self.sample_pixel(i, j, adaptive.min_samples, sampler.as_mut(), &mut stats);
+                        while !adaptive.is_converged(&stats) {
+                            let batch = adaptive.batch_size.min(adaptive.max_samples - stats.count);
+                            self.sample_pixel(i, j, batch, sampler.as_mut(), &mut stats);
+                        }
+                    },
+                    None => self.sample_pixel(i, j, self.rays_per_pixel, sampler.as_mut(), &mut stats)
+                }
+                pixels.push(stats);
+            }
+        }
+        Some(pixels)
+    }
 
-        color = color + Vec3::new(1.0, 1.0, 1.0) * specular_light_intensity * material.specular_albedo;
+    fn sample_pixel(&self, i: usize, j: usize, count: usize, sampler: &mut dyn Sampler, stats: &mut PixelStats) {
+        for _ in 0..count {
+            sampler.start_sample(stats.count);
+            let ray = self.camera.compute_camera(i, j, sampler.next_2d());
+            stats.push(&self.integrator.radiance(self, &ray, sampler));
+        }
+    }
 
-        color = color + reflect_color * material.reflexion_factor;
+    // Every pixel gets its own random stream derived from the scene seed, so the
+    // image only depends on the seed and not on which thread rendered the pixel
+    fn pixel_seed(&self, i: usize, j: usize) -> u64 {
+        let index = (j * self.camera.width + i) as u64;
+        sampler::hash(self.seed ^ sampler::hash(index))
+    }
+}
 
-        color = color + refract_color * material.transparency_factor;
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
 
-        color
-    }
+    use super::*;
+    use crate::{
+        light::PointLight,
+        material::Material,
+        sphere::Sphere,
+        checker_board::CheckerBoard,
+        integrator::PathIntegrator
+    };
 
-    fn reflect(impident: &Vec3, normal: &Vec3) -> Vec3 {
-        impident - normal * 2.0 * Vec3::dot(impident, normal)
+    // Sky in the upper half of the image, a floor lit by it in the lower half
+    fn sky_and_floor() -> Scene {
+        let grey = Material::new(Vec3::new(1.0, 1.0, 1.0), 0.5, 10.0, 0.0, 0.0, 1.0, 0.0);
+        let mut scene = Scene::new(4, 2, Camera::new(Vec3::new(0.0, 0.0, 0.0), 16, 8, std::f32::consts::PI / 2.0));
+        scene.set_integrator(PathIntegrator::default());
+        scene.push_object(CheckerBoard::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, grey.clone(), grey));
+        scene
     }
 
-    fn refract(impident: &Vec3, normal: &Vec3, refraction_index: f32) -> Vec3 {
-        let mut cosi = -Vec3::dot(&impident, &normal).min(1.0).max(-1.0);
-        let mut etai = 1.0;
-        let mut etat = refraction_index;
-        let mut n = normal.clone();
-        if cosi < 0.0 {
-            cosi = -cosi;
-            n = -normal;
-            std::mem::swap(&mut etai, &mut etat);
+    #[test]
+    fn adaptive_sampling_stops_on_flat_pixels() {
+        let mut scene = sky_and_floor();
+        scene.set_adaptive_sampling(Some(AdaptiveSampling::new(4, 64, 0.001)));
+        let framebuffer = scene.render().unwrap();
+        for x in 0..16 {
+            assert_eq!(framebuffer.sample_count(x, 0), 4);
+            assert_eq!(framebuffer.sample_count(x, 7), 64);
         }
-        let eta = etai / etat;
-        let k = 1.0 - etat * etat * (1.0 - cosi * cosi);
-        if k > 0.0 {
-            return impident * eta + n * (eta * cosi - k.sqrt())
+
+        let mut adaptive = AdaptiveSampling::new(4, 64, 0.001);
+        adaptive.batch_size = 0;
+        scene.set_adaptive_sampling(Some(adaptive));
+        assert!(matches!(scene.render(), Err(RenderError::InvalidSettings(_))));
+    }
+
+    // Cancels the render as soon as the first tile comes back
+    struct CancelOnFirstTile {
+        cancel: CancelToken,
+        finished: Arc<Mutex<Option<(usize, bool)>>>
+    }
+
+    impl RenderObserver for CancelOnFirstTile {
+        fn on_progress(&self, _progress: &Progress) {
+            self.cancel.cancel()
         }
-        Vec3::new(0.0, 0.0, 0.0)
-    }
-    
-    fn get_color(&self, color: Vec3) -> [u8; 3] {
-        let max = color.x.max(color.y.max(color.z));
-        
-        let mut return_value = color;
-        if max > self.rays_per_pixel as f32 {
-            return_value = return_value * (self.rays_per_pixel as f32 / max) 
+
+        fn on_finish(&self, progress: &Progress, cancelled: bool) {
+            *self.finished.lock().unwrap() = Some((progress.completed_tiles, cancelled));
         }
+    }
 
-        let scale = 1.0 / self.rays_per_pixel as f32;
+    #[test]
+    fn cancelling_from_an_observer_stops_the_render() {
+        let mut scene = sky_and_floor();
+        scene.set_resolution(128, 128);
+        scene.set_tile_size(4);
+        scene.set_threads(2);
+        let cancel = CancelToken::new();
+        let finished = Arc::new(Mutex::new(None));
+        scene.push_observer(CancelOnFirstTile { cancel: cancel.clone(), finished: finished.clone() });
 
-        let r = (((return_value.x.min(self.rays_per_pixel as f32)).max(0.0) * 255.0) * scale) as u8;
-        let g = (((return_value.y.min(self.rays_per_pixel as f32)).max(0.0) * 255.0) * scale) as u8;
-        let b = (((return_value.z.min(self.rays_per_pixel as f32)).max(0.0) * 255.0) * scale) as u8;
+        let framebuffer = scene.render_cancellable(&cancel).unwrap();
+        let (completed_tiles, cancelled) = finished.lock().unwrap().unwrap();
+        assert!(cancelled);
+        assert!(completed_tiles > 0 && completed_tiles < 32 * 32);
 
-        [r, g, b]
+        // Every tile is either fully rendered or not touched at all
+        let tiles = Tile::split(128, 128, 4);
+        let rendered = tiles.iter().filter(|tile| {
+            let counts: Vec<_> = (0..tile.pixel_count())
+                .map(|n| framebuffer.sample_count(tile.x + n % tile.width, tile.y + n / tile.width))
+                .collect();
+            assert!(counts.iter().all(|&count| count == 4) || counts.iter().all(|&count| count == 0));
+            counts[0] == 4
+        }).count();
+        assert_eq!(rendered, completed_tiles);
     }
 
-    fn save_as_png(&self, path: &str, data: Vec<u8>) {
-        let path = Path::new(path);
-        let file = File::create(path).unwrap();
-        let ref mut w = BufWriter::new(file);
-
-        let mut encoder = png::Encoder::new(w, self.camera.width as u32, self.camera.height as u32); 
-        encoder.set_color(png::ColorType::RGB);
-        encoder.set_depth(png::BitDepth::Eight);
-        let mut writer = encoder.write_header().unwrap();
+    #[test]
+    fn images_do_not_depend_on_threads_and_tiles() {
+        let mut scene = Scene::new(4, 2, Camera::new(Vec3::new(0.0, 0.0, 0.0), 16, 8, 1.0));
+        let red = Material::new(Vec3::new(0.8, 0.2, 0.2), 0.8, 20.0, 0.3, 0.1, 1.0, 0.0);
+        scene.push_object(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 2.0, red));
+        scene.push_light(PointLight::new(Vec3::new(5.0, 5.0, 0.0), 1.5));
+        scene.set_sampler(SamplerKind::Stratified);
+        scene.set_seed(7);
+        let render = |scene: &mut Scene, threads: usize, tile_size: usize| {
+            scene.set_threads(threads);
+            scene.set_tile_size(tile_size);
+            let framebuffer = scene.render().unwrap();
+            (0..16 * 8).map(|i| {
+                let color = framebuffer.get(i % 16, i / 16);
+                [color.x.to_bits(), color.y.to_bits(), color.z.to_bits()]
+            }).collect::<Vec<_>>()
+        };
 
-        writer.write_image_data(&data).unwrap();
+        let reference = render(&mut scene, 1, 32);
+        assert_eq!(render(&mut scene, 4, 3), reference);
+        assert_eq!(render(&mut scene, 3, 5), reference);
     }
-}
\ No newline at end of file
+}
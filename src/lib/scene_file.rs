@@ -0,0 +1,759 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    scene::Scene,
+    vec3::Vec3,
+    background::{Environment, ConstantBackground, GradientBackground, EnvironmentMap},
+    light::{Falloff, PointLight, DirectionalLight, SpotLight, HemisphereLight, RectangleLight, DiskLight, SphereLight},
+    mesh::TriangleMesh,
+    material::{Material, Fresnel},
+    sphere::Sphere,
+    checker_board::CheckerBoard,
+    triangle::Triangle,
+    camera::Camera,
+    sampler::SamplerKind,
+    adaptive::AdaptiveSampling,
+    integrator::{WhittedIntegrator, PathIntegrator},
+    obj,
+    ply,
+    hdr,
+    gltf
+};
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io {
+        path: PathBuf,
+        error: io::Error
+    },
+    // The file is not valid TOML or does not match the format, the toml error
+    // shows the location itself
+    Syntax {
+        path: PathBuf,
+        error: toml::de::Error
+    },
+    Invalid {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String
+    },
+    // A model referenced by the scene could not be loaded
+    Import {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        error: Box<dyn Error>
+    }
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneFileError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            SceneFileError::Syntax { path, error } => write!(f, "{}: {}", path.display(), error),
+            SceneFileError::Invalid { path, line, column, message } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            SceneFileError::Import { path, line, column, error } => write!(f, "{}:{}:{}: {}", path.display(), line, column, error)
+        }
+    }
+}
+
+impl Error for SceneFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneFileError::Io { error, .. } => Some(error),
+            SceneFileError::Syntax { error, .. } => Some(error),
+            SceneFileError::Invalid { .. } => None,
+            SceneFileError::Import { error, .. } => Some(error.as_ref())
+        }
+    }
+}
+
+// A scene built from a file, along with what could not be represented in it
+pub struct SceneFile {
+    pub scene: Scene,
+    pub warnings: Vec<String>
+}
+
+// Builds a scene from a TOML description, see scenes/default.toml. Paths of
+// imported models are relative to the scene file.
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<SceneFile, SceneFileError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|error| SceneFileError::Io {
+        path: path.to_path_buf(),
+        error
+    })?;
+    parse_scene(&source, path)
+}
+
+// `path` is only used to report errors and find the imported models
+pub fn parse_scene(source: &str, path: &Path) -> Result<SceneFile, SceneFileError> {
+    let description: SceneDescription = toml::from_str(source).map_err(|error| SceneFileError::Syntax {
+        path: path.to_path_buf(),
+        error
+    })?;
+    Builder {
+        source,
+        path,
+        warnings: vec![]
+    }.build(description)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    render: RenderDescription,
+    camera: CameraDescription,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDescription>>,
+    #[serde(default)]
+    lights: Vec<Spanned<LightDescription>>,
+    background: Option<Spanned<BackgroundDescription>>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenderDescription {
+    width: usize,
+    height: usize,
+    rays_per_pixel: usize,
+    // Older scene files use the names the code had
+    #[serde(alias = "max_recurtion")]
+    max_depth: usize,
+    threads: Option<usize>,
+    tile_size: Option<usize>,
+    seed: Option<u64>,
+    sampler: Option<SamplerName>,
+    integrator: Option<IntegratorDescription>,
+    adaptive: Option<AdaptiveDescription>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SamplerName {
+    Independent,
+    Stratified,
+    Halton,
+    R2
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum IntegratorDescription {
+    Whitted,
+    Path {
+        roulette_depth: Option<usize>
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AdaptiveDescription {
+    min_samples: usize,
+    max_samples: usize,
+    threshold: f32,
+    batch_size: Option<usize>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    position: [f32; 3],
+    // Vertical field of view, in degrees
+    fov: f32,
+    // Rotations around x, y then z, in degrees
+    #[serde(default)]
+    rotation: [f32; 3]
+}
+
+// Every kind can be turned around the vertical axis by `rotation` degrees and
+// scaled by `intensity`
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundDescription {
+    Constant {
+        color: [f32; 3],
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32
+    },
+    Gradient {
+        bottom: [f32; 3],
+        top: [f32; 3],
+        #[serde(default = "default_up")]
+        up: [f32; 3],
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32
+    },
+    // Equirectangular Radiance .hdr image
+    Hdr {
+        path: PathBuf,
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32
+    }
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDescription {
+    color: [f32; 3],
+    color_albedo: f32,
+    specular_exponent: f32,
+    #[serde(default)]
+    specular_albedo: f32,
+    #[serde(default, alias = "reflexion_factor")]
+    reflection: f32,
+    #[serde(default = "default_refraction_index")]
+    refraction_index: f32,
+    #[serde(default)]
+    transparency_factor: f32,
+    fresnel: Option<FresnelName>,
+    // Objects with an emission are sampled as lights
+    emission: Option<[f32; 3]>,
+    #[serde(default = "default_emission_strength")]
+    emission_strength: f32
+}
+
+fn default_refraction_index() -> f32 {
+    1.0
+}
+
+fn default_emission_strength() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FresnelName {
+    Exact,
+    Schlick
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDescription {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String
+    },
+    CheckerBoard {
+        origin: [f32; 3],
+        normal: [f32; 3],
+        tile_size: f32,
+        materials: [String; 2],
+        dimension: Option<[f32; 2]>,
+        // In degrees
+        rotation: Option<f32>
+    },
+    Triangle {
+        vertices: [[f32; 3]; 3],
+        material: String
+    },
+    Obj {
+        path: PathBuf,
+        // Used for the faces without an MTL material
+        material: String
+    },
+    Ply {
+        path: PathBuf,
+        material: String
+    },
+    Gltf {
+        path: PathBuf,
+        // Whether the camera of the file replaces the one of the scene
+        #[serde(default)]
+        use_camera: bool
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LightDescription {
+    Point {
+        position: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        intensity: f32,
+        falloff: Option<FalloffName>
+    },
+    Directional {
+        // Direction the light travels in
+        direction: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        intensity: f32
+    },
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        intensity: f32,
+        falloff: Option<FalloffName>,
+        // Half angle of the cone, in degrees
+        cone_angle: f32,
+        // Angle where the light starts fading out, in degrees
+        inner_angle: Option<f32>
+    },
+    Hemisphere {
+        #[serde(default = "default_up")]
+        up: [f32; 3],
+        sky_color: [f32; 3],
+        ground_color: [f32; 3]
+    },
+    // Emits on the side of edge_u × edge_v
+    Rectangle {
+        center: [f32; 3],
+        edge_u: [f32; 3],
+        edge_v: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        intensity: f32,
+        // Shadow rays per shading point
+        samples: Option<usize>
+    },
+    Disk {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        intensity: f32,
+        samples: Option<usize>
+    },
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        intensity: f32,
+        samples: Option<usize>
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FalloffName {
+    InverseSquare,
+    Constant
+}
+
+fn default_light_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn vec3([x, y, z]: [f32; 3]) -> Vec3 {
+    Vec3::new(x, y, z)
+}
+
+fn falloff_from(falloff: &Option<FalloffName>) -> Falloff {
+    match falloff {
+        Some(FalloffName::InverseSquare) | None => Falloff::InverseSquare,
+        Some(FalloffName::Constant) => Falloff::Constant
+    }
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    path: &'a Path,
+    warnings: Vec<String>
+}
+
+impl Builder<'_> {
+    fn build(mut self, description: SceneDescription) -> Result<SceneFile, SceneFileError> {
+        let render = description.render;
+        let camera = &description.camera;
+        let mut scene_camera = Camera::new(vec3(camera.position), render.width, render.height, camera.fov.to_radians());
+        scene_camera.set_rotation_x(camera.rotation[0].to_radians());
+        scene_camera.set_rotation_y(camera.rotation[1].to_radians());
+        scene_camera.set_rotation_z(camera.rotation[2].to_radians());
+
+        let mut scene = Scene::new(render.rays_per_pixel, render.max_depth, scene_camera);
+        if let Some(threads) = render.threads {
+            scene.set_threads(threads);
+        }
+        if let Some(tile_size) = render.tile_size {
+            scene.set_tile_size(tile_size);
+        }
+        if let Some(seed) = render.seed {
+            scene.set_seed(seed);
+        }
+        if let Some(sampler) = render.sampler {
+            scene.set_sampler(match sampler {
+                SamplerName::Independent => SamplerKind::Independent,
+                SamplerName::Stratified => SamplerKind::Stratified,
+                SamplerName::Halton => SamplerKind::Halton,
+                SamplerName::R2 => SamplerKind::R2
+            });
+        }
+        match render.integrator {
+            Some(IntegratorDescription::Whitted) | None => scene.set_integrator(WhittedIntegrator),
+            Some(IntegratorDescription::Path { roulette_depth: Some(depth) }) => scene.set_integrator(PathIntegrator::new(depth)),
+            Some(IntegratorDescription::Path { roulette_depth: None }) => scene.set_integrator(PathIntegrator::default())
+        }
+        if let Some(adaptive) = render.adaptive {
+            // Kept as written, rendering rejects the invalid combinations
+            scene.set_adaptive_sampling(Some(AdaptiveSampling {
+                min_samples: adaptive.min_samples,
+                max_samples: adaptive.max_samples,
+                batch_size: adaptive.batch_size.unwrap_or(adaptive.min_samples),
+                threshold: adaptive.threshold
+            }));
+        }
+
+        if let Some(background) = &description.background {
+            scene.set_environment(self.environment(background)?);
+        }
+
+        let materials: HashMap<&str, Material> = description.materials.iter().map(|(name, material)| {
+            let converted = Material::new(
+                vec3(material.color),
+                material.color_albedo,
+                material.specular_exponent,
+                material.specular_albedo,
+                material.reflection,
+                material.refraction_index,
+                material.transparency_factor
+            );
+            let converted = match material.fresnel {
+                Some(FresnelName::Exact) => converted.with_fresnel(Fresnel::Exact),
+                Some(FresnelName::Schlick) => converted.with_fresnel(Fresnel::Schlick),
+                None => converted
+            };
+            let converted = match material.emission {
+                Some(emission) => converted.with_emission(vec3(emission), material.emission_strength),
+                None => converted
+            };
+            (name.as_str(), converted)
+        }).collect();
+
+        for object in &description.objects {
+            self.push_object(&mut scene, &materials, object)?;
+        }
+        for light in &description.lights {
+            self.push_light(&mut scene, light)?;
+        }
+
+        Ok(SceneFile {
+            scene,
+            warnings: self.warnings
+        })
+    }
+
+    fn push_object(&mut self, scene: &mut Scene, materials: &HashMap<&str, Material>, object: &Spanned<ObjectDescription>) -> Result<(), SceneFileError> {
+        let span = object.span();
+        let material = |name: &String| match materials.get(name.as_str()) {
+            Some(material) => Ok(material.clone()),
+            None => Err(self.invalid(&span, format!("unknown material `{}`", name)))
+        };
+
+        match object.get_ref() {
+            ObjectDescription::Sphere { center, radius, material: name } => {
+                scene.push_object(Sphere::new(vec3(*center), self.radius(&span, *radius)?, material(name)?));
+            },
+            ObjectDescription::CheckerBoard { origin, normal, tile_size, materials: [first, second], dimension, rotation } => {
+                let (first, second) = (material(first)?, material(second)?);
+                if first.is_emissive() || second.is_emissive() {
+                    return Err(self.invalid(&span, String::from("checker boards cannot emit light, use a rectangle light instead")))
+                }
+                let normal = self.direction(&span, "normal", normal)?;
+                let mut board = CheckerBoard::new(vec3(*origin), normal, *tile_size, first, second);
+                if let Some([width, depth]) = dimension {
+                    board = board.with_dimension(*width, *depth);
+                }
+                if let Some(angle) = rotation {
+                    board = board.with_rotation(angle.to_radians());
+                }
+                scene.push_object(board);
+            },
+            ObjectDescription::Triangle { vertices: [a, b, c], material: name } => {
+                scene.push_object(Triangle::new(vec3(*a), vec3(*b), vec3(*c), material(name)?));
+            },
+            ObjectDescription::Obj { path, material: name } => {
+                let default_material = material(name)?;
+                let groups = obj::load_obj(self.model_path(path), &default_material).map_err(|error| self.import(&span, error))?;
+                for group in groups {
+                    self.check_normals(&format!("{}: group `{}`", path.display(), group.name), &group.mesh);
+                    scene.push_object(group.mesh);
+                }
+            },
+            ObjectDescription::Ply { path, material: name } => {
+                let mesh = ply::load_ply(self.model_path(path), material(name)?).map_err(|error| self.import(&span, error))?;
+                self.check_normals(&path.display().to_string(), &mesh);
+                scene.push_object(mesh);
+            },
+            ObjectDescription::Gltf { path, use_camera } => {
+                let mut imported = gltf::load_gltf(self.model_path(path)).map_err(|error| self.import(&span, error))?;
+                if !use_camera {
+                    imported.cameras.clear();
+                }
+                let mut warnings = imported.push_into(scene);
+                self.warnings.append(&mut warnings);
+            }
+        }
+        Ok(())
+    }
+
+    fn push_light(&self, scene: &mut Scene, light: &Spanned<LightDescription>) -> Result<(), SceneFileError> {
+        let span = light.span();
+        match light.get_ref() {
+            LightDescription::Point { position, color, intensity, falloff } => {
+                let point = PointLight::new(vec3(*position), *intensity)
+                    .with_color(vec3(*color))
+                    .with_falloff(falloff_from(falloff));
+                scene.push_light(point);
+            },
+            LightDescription::Directional { direction, color, intensity } => {
+                let direction = self.direction(&span, "direction", direction)?;
+                scene.push_light(DirectionalLight::new(direction, *intensity).with_color(vec3(*color)));
+            },
+            LightDescription::Spot { position, direction, color, intensity, falloff, cone_angle, inner_angle } => {
+                let direction = self.direction(&span, "direction", direction)?;
+                let mut spot = SpotLight::new(vec3(*position), direction, *intensity, cone_angle.to_radians())
+                    .with_color(vec3(*color))
+                    .with_falloff(falloff_from(falloff));
+                if let Some(angle) = inner_angle {
+                    spot = spot.with_inner_angle(angle.to_radians());
+                }
+                scene.push_light(spot);
+            },
+            LightDescription::Hemisphere { up, sky_color, ground_color } => {
+                let up = self.direction(&span, "up", up)?;
+                scene.push_light(HemisphereLight::new(up, vec3(*sky_color), vec3(*ground_color)));
+            },
+            LightDescription::Rectangle { center, edge_u, edge_v, color, intensity, samples } => {
+                let mut rectangle = RectangleLight::new(vec3(*center), vec3(*edge_u), vec3(*edge_v), *intensity).with_color(vec3(*color));
+                if let Some(samples) = samples {
+                    rectangle = rectangle.with_samples(*samples);
+                }
+                scene.push_light(rectangle);
+            },
+            LightDescription::Disk { center, normal, radius, color, intensity, samples } => {
+                let (normal, radius) = (self.direction(&span, "normal", normal)?, self.radius(&span, *radius)?);
+                let mut disk = DiskLight::new(vec3(*center), normal, radius, *intensity).with_color(vec3(*color));
+                if let Some(samples) = samples {
+                    disk = disk.with_samples(*samples);
+                }
+                scene.push_light(disk);
+            },
+            LightDescription::Sphere { center, radius, color, intensity, samples } => {
+                let mut sphere = SphereLight::new(vec3(*center), self.radius(&span, *radius)?, *intensity).with_color(vec3(*color));
+                if let Some(samples) = samples {
+                    sphere = sphere.with_samples(*samples);
+                }
+                scene.push_light(sphere);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_normals(&mut self, name: &str, mesh: &TriangleMesh) {
+        let count = mesh.degenerate_normal_count();
+        if count > 0 {
+            self.warnings.push(format!("{}: {} normals have no direction, the face normal is used there", name, count));
+        }
+    }
+
+    // Normals and directions are normalized when used, so they need a length
+    fn direction(&self, span: &Range<usize>, name: &str, value: &[f32; 3]) -> Result<Vec3, SceneFileError> {
+        let direction = vec3(*value);
+        let length = direction.lenght();
+        if length == 0.0 || !length.is_finite() {
+            return Err(self.invalid(span, format!("`{}` must have a non zero, finite length", name)))
+        }
+        Ok(direction)
+    }
+
+    fn radius(&self, span: &Range<usize>, radius: f32) -> Result<f32, SceneFileError> {
+        if !(radius > 0.0 && radius.is_finite()) {
+            return Err(self.invalid(span, format!("`radius` must be positive, not {}", radius)))
+        }
+        Ok(radius)
+    }
+
+    fn environment(&self, background: &Spanned<BackgroundDescription>) -> Result<Environment, SceneFileError> {
+        let (environment, rotation, intensity) = match background.get_ref() {
+            BackgroundDescription::Constant { color, rotation, intensity } => {
+                (Environment::new(ConstantBackground::new(vec3(*color))), rotation, intensity)
+            },
+            BackgroundDescription::Gradient { bottom, top, up, rotation, intensity } => {
+                (Environment::new(GradientBackground::new(vec3(*bottom), vec3(*top)).with_up(vec3(*up))), rotation, intensity)
+            },
+            BackgroundDescription::Hdr { path, rotation, intensity } => {
+                let image = hdr::load_hdr(self.model_path(path)).map_err(|error| self.import(&background.span(), error))?;
+                (Environment::new(EnvironmentMap::new(image)), rotation, intensity)
+            }
+        };
+        Ok(environment.with_rotation(rotation.to_radians()).with_intensity(*intensity))
+    }
+
+    fn model_path(&self, path: &Path) -> PathBuf {
+        self.path.parent().unwrap_or_else(|| Path::new("")).join(path)
+    }
+
+    // 1 based line and column of a byte offset in the source
+    fn location(&self, span: &Range<usize>) -> (usize, usize) {
+        let before = &self.source[..span.start.min(self.source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+        (line, column)
+    }
+
+    fn invalid(&self, span: &Range<usize>, message: String) -> SceneFileError {
+        let (line, column) = self.location(span);
+        SceneFileError::Invalid {
+            path: self.path.to_path_buf(),
+            line,
+            column,
+            message
+        }
+    }
+
+    fn import<E: Error + 'static>(&self, span: &Range<usize>, error: E) -> SceneFileError {
+        let (line, column) = self.location(span);
+        SceneFileError::Import {
+            path: self.path.to_path_buf(),
+            line,
+            column,
+            error: Box::new(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "
+[render]
+width = 64
+height = 32
+rays_per_pixel = 2
+max_depth = 3
+integrator = { type = \"path\" }
+
+[camera]
+position = [0.0, 0.0, 0.0]
+fov = 60.0
+
+[materials.red]
+color = [1.0, 0.0, 0.0]
+color_albedo = 0.9
+specular_exponent = 10.0
+";
+
+    #[test]
+    fn the_default_scene_loads() {
+        let file = load_scene(Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/default.toml")).unwrap();
+        assert!(file.warnings.is_empty());
+        assert_eq!(file.scene.lights().len(), 3);
+        assert_eq!(file.scene.camera().width, 1920);
+    }
+
+    #[test]
+    fn unknown_materials_point_at_the_object() {
+        let source = format!("{}\n[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, -5.0]\nradius = 1.0\nmaterial = \"blue\"\n", HEADER);
+        match parse_scene(&source, Path::new("test.toml")) {
+            Err(SceneFileError::Invalid { line, message, .. }) => {
+                assert_eq!(line, 18);
+                assert!(message.contains("`blue`"), "{}", message);
+            },
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("the scene should not load")
+        }
+    }
+
+    #[test]
+    fn syntax_errors_have_a_location() {
+        let source = format!("{}\n[[lights]]\ntype = \"point\"\nposition = [0.0, 1.0]\nintensity = 1.0\n", HEADER);
+        match parse_scene(&source, Path::new("test.toml")) {
+            Err(error @ SceneFileError::Syntax { .. }) => assert!(error.to_string().contains("line 18"), "{}", error),
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("the scene should not load")
+        }
+    }
+
+    #[test]
+    fn shapes_without_a_direction_or_size_point_at_their_entry() {
+        let invalid = |entries: &str| match parse_scene(&format!("{}{}", HEADER, entries), Path::new("test.toml")) {
+            Err(SceneFileError::Invalid { line, message, .. }) => (line, message),
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("the scene should not load")
+        };
+
+        let (line, message) = invalid("\n[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, -5.0]\nradius = -1.0\nmaterial = \"red\"\n");
+        assert_eq!(line, 18);
+        assert!(message.contains("`radius` must be positive"), "{}", message);
+
+        let (line, message) = invalid("\n[[objects]]\ntype = \"checker_board\"\norigin = [0.0, -1.0, 0.0]\nnormal = [0.0, 0.0, 0.0]\ntile_size = 1.0\nmaterials = [\"red\", \"red\"]\n");
+        assert_eq!(line, 18);
+        assert!(message.contains("`normal` must have a non zero"), "{}", message);
+
+        let (line, message) = invalid("\n[[lights]]\ntype = \"point\"\nposition = [0.0, 1.0, 0.0]\nintensity = 1.0\n\n[[lights]]\ntype = \"directional\"\ndirection = [0.0, 0.0, 0.0]\nintensity = 1.0\n");
+        assert_eq!(line, 23);
+        assert!(message.contains("`direction` must have a non zero"), "{}", message);
+
+        let (_, message) = invalid("\n[[lights]]\ntype = \"sphere\"\ncenter = [0.0, 1.0, 0.0]\nradius = 0.0\nintensity = 1.0\n");
+        assert!(message.contains("`radius` must be positive"), "{}", message);
+    }
+
+    #[test]
+    fn emissive_objects_are_lights() {
+        let glow = "[materials.glow]\ncolor = [0.0, 0.0, 0.0]\ncolor_albedo = 0.0\nspecular_exponent = 1.0\nemission = [1.0, 1.0, 1.0]\n";
+        let source = format!("{}{}\n[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, -5.0]\nradius = 1.0\nmaterial = \"glow\"\n", HEADER, glow);
+        let file = parse_scene(&source, Path::new("test.toml")).unwrap();
+        assert_eq!(file.scene.lights().len(), 1);
+
+        let source = format!("{}{}\n[[objects]]\ntype = \"checker_board\"\norigin = [0.0, -1.0, 0.0]\nnormal = [0.0, 1.0, 0.0]\ntile_size = 1.0\nmaterials = [\"red\", \"glow\"]\n", HEADER, glow);
+        match parse_scene(&source, Path::new("test.toml")) {
+            Err(SceneFileError::Invalid { message, .. }) => assert!(message.contains("cannot emit"), "{}", message),
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("the scene should not load")
+        }
+    }
+
+    #[test]
+    fn backgrounds_are_rotated_and_scaled() {
+        let source = format!("{}\n[background]\ntype = \"gradient\"\nbottom = [0.0, 0.0, 0.0]\ntop = [1.0, 1.0, 1.0]\nup = [1.0, 0.0, 0.0]\nrotation = 90.0\nintensity = 3.0\n", HEADER);
+        let file = parse_scene(&source, Path::new("test.toml")).unwrap();
+        let radiance = file.scene.background(&Vec3::new(0.0, 0.0, -1.0));
+        assert!((radiance.x - 3.0).abs() < 1e-5, "{:?}", radiance);
+
+        let source = format!("{}\n[background]\ntype = \"hdr\"\npath = \"missing.hdr\"\n", HEADER);
+        assert!(matches!(parse_scene(&source, Path::new("test.toml")), Err(SceneFileError::Import { line: 18, .. })));
+
+        let source = format!("{}\n[background]\ntype = \"constant\"\ncolour = [1.0, 1.0, 1.0]\n", HEADER);
+        assert!(matches!(parse_scene(&source, Path::new("test.toml")), Err(SceneFileError::Syntax { .. })));
+    }
+
+    #[test]
+    fn old_key_names_still_load() {
+        let source = HEADER.replace("max_depth", "max_recurtion") + "reflexion_factor = 0.5\n";
+        parse_scene(&source, Path::new("test.toml")).unwrap();
+    }
+}
@@ -1,7 +1,9 @@
-use crate::lib::vec3::Vec3;
-use crate::lib::ray::Ray;
-use crate::lib::hitable::{Hitable, HitInfos};
-use crate::lib::material::Material;
+use crate::vec3::Vec3;
+use crate::ray::Ray;
+use crate::hitable::{Hitable, HitInfos};
+use crate::material::Material;
+use crate::aabb::Aabb;
+use crate::light::{Light, SphereLight, ObjectLight};
 
 #[derive(Debug, Clone)]
 pub struct Sphere {
@@ -18,47 +20,68 @@ impl Sphere {
             material
         }
     }
+
+    // Distances along the ray where it enters and leaves the sphere
+    fn roots(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let oc = &ray.origin - &self.center;
+
+        let a = ray.direction.lenght_squared();
+        let half_b = Vec3::dot(&oc, &ray.direction);
+        let c = oc.lenght_squared() - self.radius * self.radius;
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return None
+        }
+        let sqrt_discriminant = discriminant.sqrt();
+        Some(((-half_b - sqrt_discriminant) / a, (-half_b + sqrt_discriminant) / a))
+    }
+
+    // A ray starting inside the sphere hits it where it leaves
+    fn first_root(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<f32> {
+        let (near, far) = self.roots(ray)?;
+        if near >= t_min && near <= t_max {
+            Some(near)
+        } else if far >= t_min && far <= t_max {
+            Some(far)
+        } else {
+            None
+        }
+    }
 }
 
 impl Hitable for Sphere {
-    fn ray_intersect(&self, ray: &Ray) -> Option<HitInfos> {
-        let v = &self.center - &ray.origin;
+    fn ray_intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitInfos<'_>> {
+        let hit_distance = self.first_root(ray, t_min, t_max)?;
 
-        let p = Vec3::dot(&v, &ray.direction);
-        if p <= 0.0 {
-            // The sphere is behind the ray
-            return None;
-        } 
+        let hit_point = &ray.origin + &(&ray.direction * hit_distance);
+        let normal = (&hit_point - &self.center).normalize();
 
-        // Projection of center on the ray
-        let pc = &ray.direction * (Vec3::dot(&ray.direction, &v) / ray.direction.lenght());
+        Some(HitInfos {
+            hit_point,
+            hit_distance,
+            normal,
+            material: &self.material,
+            barycentric: None,
+            color: None
+        })
+    }
 
-        // Distance between pc and the center 
-        let d = (&self.center - &(&ray.origin + &pc)).lenght(); 
+    fn occluded(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.first_root(ray, t_min, t_max).is_some()
+    }
 
-        if d < self.radius {
-            // Distance between the hit point and pc
-            let a = (self.radius * self.radius - d * d).sqrt();
-            let hit_distance = p - a;
-            
-            let exit_distance = p + a;
-            
-            if hit_distance < 0.0 {
-                return None
-            }
+    fn bounding_box(&self) -> Aabb {
+        let radius = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(&self.center - &radius, &self.center + &radius)
+    }
 
-            let hit_point = &ray.origin + &(&ray.direction * hit_distance); 
-            let exit_point = &ray.origin + &(&ray.direction * exit_distance); 
-            let normal = (&hit_point - &self.center).normalize();
-            
-            return Some (HitInfos {
-                hit_point,
-                exit_point,
-                hit_distance,
-                normal,
-                material: self.material.clone()
-            });
-        }        
-        None
+    fn emitter(&self) -> Result<Option<Box<dyn Light>>, String> {
+        if !self.material.is_emissive() {
+            return Ok(None)
+        }
+        let light = SphereLight::new(self.center.clone(), self.radius, self.material.emission_strength)
+            .with_color(self.material.emission_color.clone());
+        Ok(Some(Box::new(ObjectLight::new(light))))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,35 @@
+#[derive(Debug, Clone)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize
+}
+
+impl Tile {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Tile {
+        Tile {
+            x,
+            y,
+            width,
+            height
+        }
+    }
+
+    // Cuts the image into size x size tiles, row by row. Tiles on the right
+    // and bottom edges are clipped to the image.
+    pub fn split(width: usize, height: usize, size: usize) -> Vec<Tile> {
+        let size = size.max(1);
+        let mut tiles = vec![];
+        for y in (0..height).step_by(size) {
+            for x in (0..width).step_by(size) {
+                tiles.push(Tile::new(x, y, size.min(width - x), size.min(height - y)));
+            }
+        }
+        tiles
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.width * self.height
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    vec3::Vec3,
+    ray::Ray,
+    material::Material,
+    aabb::Aabb,
+    mesh::TriangleMesh,
+    light::{Light, MeshLight, ObjectLight},
+    hitable::{Hitable, HitInfos}
+};
+
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub vertices: [Vec3; 3],
+    // Per vertex normals for smooth shading, the flat normal is used otherwise
+    pub normals: Option<[Vec3; 3]>,
+    pub material: Material
+}
+
+impl Triangle {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Material) -> Triangle {
+        Triangle {
+            vertices: [a, b, c],
+            normals: None,
+            material
+        }
+    }
+
+    pub fn with_normals(mut self, a: Vec3, b: Vec3, c: Vec3) -> Triangle {
+        self.normals = Some([unit_or_zero(&a), unit_or_zero(&b), unit_or_zero(&c)]);
+        self
+    }
+}
+
+impl Hitable for Triangle {
+    fn ray_intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitInfos<'_>> {
+        let [a, b, c] = &self.vertices;
+        let (hit_distance, u, v) = intersect(ray, a, b, c, t_min, t_max)?;
+        let normal = shading_normal(a, b, c, self.normals.as_ref().map(|[na, nb, nc]| (na, nb, nc)), u, v);
+
+        Some(HitInfos {
+            hit_point: &ray.origin + &(&ray.direction * hit_distance),
+            hit_distance,
+            normal,
+            material: &self.material,
+            barycentric: Some((u, v)),
+            color: None
+        })
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        let [a, b, c] = &self.vertices;
+        intersect(ray, a, b, c, t_min, t_max).is_some()
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.vertices.iter().fold(Aabb::empty(), |bounds, vertex| bounds.grow(vertex))
+    }
+
+    fn emitter(&self) -> Result<Option<Box<dyn Light>>, String> {
+        if !self.material.is_emissive() {
+            return Ok(None)
+        }
+        let mesh = TriangleMesh::new(self.vertices.to_vec(), vec![[0, 1, 2]], self.material.clone()).map_err(|error| error.to_string())?;
+        Ok(Some(Box::new(ObjectLight::new(MeshLight::new(mesh)))))
+    }
+}
+
+// Watertight ray/triangle intersection (Woop, Benthin and Wald 2013): the
+// triangle is moved into a space where the ray goes along +z from the origin,
+// so rays hitting a shared edge never slip between two triangles.
+// Returns the distance and the barycentric coordinates (u, v) of the hit,
+// the point being (1 - u - v) * a + u * b + v * c.
+pub fn intersect(ray: &Ray, a: &Vec3, b: &Vec3, c: &Vec3, t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
+    let direction = &ray.direction;
+    let abs = Vec3::new(direction.x.abs(), direction.y.abs(), direction.z.abs());
+    let kz = if abs.x > abs.y && abs.x > abs.z { 0 } else if abs.y > abs.z { 1 } else { 2 };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if direction[kz] < 0.0 {
+        // Keeps the winding of the triangle
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    let shear_x = direction[kx] / direction[kz];
+    let shear_y = direction[ky] / direction[kz];
+    let shear_z = 1.0 / direction[kz];
+
+    let a = a - &ray.origin;
+    let b = b - &ray.origin;
+    let c = c - &ray.origin;
+
+    let (ax, ay) = (a[kx] - shear_x * a[kz], a[ky] - shear_y * a[kz]);
+    let (bx, by) = (b[kx] - shear_x * b[kz], b[ky] - shear_y * b[kz]);
+    let (cx, cy) = (c[kx] - shear_x * c[kz], c[ky] - shear_y * c[kz]);
+
+    let mut edge_a = cx * by - cy * bx;
+    let mut edge_b = ax * cy - ay * cx;
+    let mut edge_c = bx * ay - by * ax;
+
+    if edge_a == 0.0 || edge_b == 0.0 || edge_c == 0.0 {
+        // On an edge in single precision, decide in double precision
+        edge_a = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+        edge_b = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+        edge_c = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+    }
+
+    if (edge_a < 0.0 || edge_b < 0.0 || edge_c < 0.0) && (edge_a > 0.0 || edge_b > 0.0 || edge_c > 0.0) {
+        return None
+    }
+
+    let determinant = edge_a + edge_b + edge_c;
+    if determinant == 0.0 {
+        return None
+    }
+
+    let scaled_distance = edge_a * shear_z * a[kz] + edge_b * shear_z * b[kz] + edge_c * shear_z * c[kz];
+    let distance = scaled_distance / determinant;
+    if !(distance >= t_min && distance <= t_max) {
+        return None
+    }
+
+    Some((distance, edge_b / determinant, edge_c / determinant))
+}
+
+// Interpolated vertex normal if there are some, the face normal otherwise or
+// where the vertex normals are degenerate or cancel out
+pub fn shading_normal(a: &Vec3, b: &Vec3, c: &Vec3, normals: Option<(&Vec3, &Vec3, &Vec3)>, u: f32, v: f32) -> Vec3 {
+    if let Some((na, nb, nc)) = normals {
+        let normal = na * (1.0 - u - v) + nb * u + nc * v;
+        let lenght = normal.lenght();
+        if lenght > 1e-6 {
+            return normal / lenght
+        }
+    }
+    Vec3::cross(&(b - a), &(c - a)).normalize()
+}
+
+// Normalized vector, or zero for vectors without a direction
+pub fn unit_or_zero(vector: &Vec3) -> Vec3 {
+    let lenght = vector.lenght();
+    if lenght > 0.0 && lenght.is_finite() {
+        vector / lenght
+    } else {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{TriangleMesh, MeshError};
+
+    fn quad() -> TriangleMesh {
+        let positions = vec![
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(-1.0, 1.0, 0.0)
+        ];
+        TriangleMesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], Material::white()).unwrap()
+    }
+
+    #[test]
+    fn rays_along_a_shared_edge_do_not_leak() {
+        let mesh = quad();
+        for n in 0..1000 {
+            // Points on the diagonal shared by the two triangles, seen from an angle
+            let t = -0.99 + 1.98 * n as f32 / 999.0;
+            let origin = Vec3::new(t + 0.3, t - 0.7, 2.0);
+            let ray = Ray::new(origin.clone(), (Vec3::new(t, t, 0.0) - origin).normalize());
+            assert!(mesh.ray_intersect(&ray, 0.0, f32::INFINITY).is_some(), "ray {} slipped through", n);
+        }
+    }
+
+    #[test]
+    fn meshes_reject_indices_past_the_positions() {
+        let positions = vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        match TriangleMesh::new(positions, vec![[0, 1, 2], [2, 1, 3]], Material::white()) {
+            Err(MeshError::Index { triangle, index, positions }) => assert_eq!((triangle, index, positions), (1, 3, 3)),
+            other => panic!("expected an index error, got {:?}", other.map(|mesh| mesh.triangle_count()))
+        }
+    }
+
+    #[test]
+    fn barycentric_coordinates_rebuild_the_hit_point() {
+        let triangle = Triangle::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(4.0, 0.0, -5.0), Vec3::new(0.0, 4.0, -5.0), Material::white());
+        let ray = Ray::new(Vec3::new(1.0, 2.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let hit = triangle.ray_intersect(&ray, 0.0, f32::INFINITY).unwrap();
+        let (u, v) = hit.barycentric.unwrap();
+        let [a, b, c] = &triangle.vertices;
+        let rebuilt = a * (1.0 - u - v) + b * u + c * v;
+
+        assert!((hit.hit_distance - 5.0).abs() < 1e-5);
+        assert!((&rebuilt - &hit.hit_point).lenght() < 1e-5);
+        assert!((u - 0.25).abs() < 1e-5 && (v - 0.5).abs() < 1e-5);
+        assert!(triangle.ray_intersect(&ray, 0.0, 4.0).is_none());
+    }
+
+    #[test]
+    fn vertex_normals_are_interpolated() {
+        let triangle = Triangle::new(Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::white())
+            .with_normals(Vec3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+        let ray = Ray::new(Vec3::new(-0.5, -0.9, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let hit = triangle.ray_intersect(&ray, 0.0, f32::INFINITY).unwrap();
+        assert!(hit.normal.x < 0.0 && hit.normal.z > 0.0);
+        assert!((hit.normal.lenght() - 1.0).abs() < 1e-5);
+    }
+}
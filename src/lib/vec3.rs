@@ -1,4 +1,4 @@
-use std::ops::{Add, Sub, Mul, Div, Neg};
+use std::ops::{Add, Sub, Mul, Div, Neg, Index};
 
 #[derive(Debug, Clone)]
 pub struct Vec3 {
@@ -39,6 +39,10 @@ impl Vec3 {
     pub fn normalize(&self) -> Vec3 {
         self / self.lenght()
     }
+
+    pub fn max_component(&self) -> f32 {
+        self.x.max(self.y.max(self.z))
+    }
 }
 
 impl Add for Vec3 {
@@ -165,6 +169,54 @@ impl Mul<f32> for &Vec3 {
     }
 }
 
+impl Mul for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, other: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x * other.x,
+            y: self.y * other.y,
+            z: self.z * other.z,
+        }
+    }
+}
+
+impl Mul for &Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, other: &Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x * other.x,
+            y: self.y * other.y,
+            z: self.z * other.z,
+        }
+    }
+}
+
+impl Mul<&Vec3> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, other: &Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x * other.x,
+            y: self.y * other.y,
+            z: self.z * other.z,
+        }
+    }
+}
+
+impl Mul<Vec3> for &Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, other: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.x * other.x,
+            y: self.y * other.y,
+            z: self.z * other.z,
+        }
+    }
+}
+
 impl Mul<Vec3> for f32 {
     type Output = Vec3;
 
@@ -267,4 +319,22 @@ impl Neg for &Vec3 {
             z: -self.z,
         }
     }
+}
+
+/// Component by axis, 0 is x, 1 is y and 2 is z.
+///
+/// # Panics
+///
+/// Panics when the axis is greater than 2.
+impl Index<usize> for Vec3 {
+    type Output = f32;
+
+    fn index(&self, axis: usize) -> &f32 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 axis out of range: {}", axis)
+        }
+    }
 }
\ No newline at end of file
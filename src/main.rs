@@ -1,73 +1,204 @@
+use std::env;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::process;
 use std::time::SystemTime;
 
-pub mod lib;
-
-use lib::{
-    scene::Scene,
-    vec3::Vec3,
-    light::Light,
-    material::Material,
-    sphere::Sphere,
-    camera::Camera
+use clap::{Parser, ValueEnum};
+
+use tiny_raytracer::{
+    Scene,
+    Framebuffer,
+    RenderError,
+    SamplerKind,
+    WhittedIntegrator,
+    PathIntegrator,
+    scene_file::{self, SceneFile},
+    progress::ConsoleProgress
 };
 
-const WIDTH: usize = 1920;
-const HEIGHT: usize = 1080;
-
-#[cfg(debug_assertions)]
-const RAYS_PER_PIXEL: usize = 1;
-
-#[cfg(not(debug_assertions))]
-const RAYS_PER_PIXEL: usize = 100;
-
-const FOV: f32 = std::f32::consts::PI / 3.0;
-
-const MAX_RECURTION: usize = 4;
-
-#[allow(unused_variables)]
-fn main() {    
-    let ivory = Material::new(Vec3::new(0.4, 0.4, 0.3), 0.6, 50.0, 0.3, 0.1, 1.0, 0.0);
-    let red_rubber = Material::new(Vec3::new(0.3, 0.1, 0.1), 0.75, 25.0, 0.2, 0.0, 1.0, 0.0);
-    let blue = Material::new(Vec3::new(0.04, 0.1, 0.3), 0.9, 40.0, 0.1, 0.1, 1.0, 0.0);
-    let mirror = Material::new(Vec3::new(1.0, 1.0, 1.), 0.0, 1425.0, 10.0, 0.8, 1.0, 0.0);
-    let glass = Material::new(Vec3::new(0.6, 0.7, 0.8), 0.0, 125.0, 0.5, 0.1, 1.5, 0.8);
-
+/// Renders a scene file to a PNG image. The options given here take
+/// precedence over the settings of the scene file.
+#[derive(Parser)]
+#[command(version, about)]
+struct Options {
+    /// Scene description to render
+    #[arg(default_value = "scenes/default.toml")]
+    scene: PathBuf,
+
+    /// Where the image is written
+    #[arg(short, long, default_value = "output.png")]
+    output: String,
+
+    /// Image width in pixels
+    #[arg(long)]
+    width: Option<NonZeroUsize>,
+
+    /// Image height in pixels
+    #[arg(long)]
+    height: Option<NonZeroUsize>,
+
+    /// Rays per pixel, replacing the adaptive sampling of the scene file
+    #[arg(short, long)]
+    spp: Option<NonZeroUsize>,
+
+    /// Maximum number of bounces
+    #[arg(short = 'd', long)]
+    max_depth: Option<usize>,
+
+    /// Worker threads
+    #[arg(short = 'j', long)]
+    threads: Option<NonZeroUsize>,
+
+    /// Seed of the random samples
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Light transport algorithm
+    #[arg(long, value_enum)]
+    integrator: Option<IntegratorName>,
+
+    /// How the samples are placed inside a pixel
+    #[arg(long, value_enum)]
+    sampler: Option<SamplerName>,
+
+    /// Also writes the number of samples taken per pixel as a heatmap
+    #[arg(long)]
+    heatmap: Option<String>,
+
+    /// Only prints warnings and errors, without the progress and timing
+    #[arg(short, long)]
+    quiet: bool
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum IntegratorName {
+    Whitted,
+    Path
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SamplerName {
+    Independent,
+    Stratified,
+    Halton,
+    R2
+}
+
+impl Options {
+    fn apply(&self, scene: &mut Scene) {
+        if self.width.is_some() || self.height.is_some() {
+            let camera = scene.camera();
+            let width = self.width.map_or(camera.width, NonZeroUsize::get);
+            let height = self.height.map_or(camera.height, NonZeroUsize::get);
+            scene.set_resolution(width, height);
+        }
+        if let Some(spp) = self.spp {
+            scene.set_adaptive_sampling(None);
+            scene.set_rays_per_pixel(spp.get());
+        }
+        if let Some(max_depth) = self.max_depth {
+            scene.set_max_recurtion(max_depth);
+        }
+        if let Some(threads) = self.threads {
+            scene.set_threads(threads.get());
+        }
+        if let Some(seed) = self.seed {
+            scene.set_seed(seed);
+        }
+        match self.integrator {
+            Some(IntegratorName::Whitted) => scene.set_integrator(WhittedIntegrator),
+            Some(IntegratorName::Path) => scene.set_integrator(PathIntegrator::default()),
+            None => {}
+        }
+        if let Some(sampler) = self.sampler {
+            scene.set_sampler(match sampler {
+                SamplerName::Independent => SamplerKind::Independent,
+                SamplerName::Stratified => SamplerKind::Stratified,
+                SamplerName::Halton => SamplerKind::Halton,
+                SamplerName::R2 => SamplerKind::R2
+            });
+        }
+    }
+}
 
-    let spheres = vec![
-        Sphere::new(Vec3::new(0.0, -31.5, -10.0), 30.0, ivory.clone()),
-        Sphere::new(Vec3::new(0.0, 0.45, -10.0), 2.0, mirror.clone()),
-        Sphere::new(Vec3::new(-6.0, -0.11, -10.0), 2.0, red_rubber.clone()),
-        Sphere::new(Vec3::new(6.0, -0.11, -10.0), 2.0, blue.clone())
-    ]; 
+fn main() {
+    if let Err(error) = run(Options::parse()) {
+        eprintln!("{}", error);
+        process::exit(1)
+    }
+}
 
-    let lights = vec![
-     Light::new(Vec3::new(-20.0, 20.0, 20.0), 1.5),
-     Light::new(Vec3::new(30.0, 50.0, -25.0), 1.8),
-     Light::new(Vec3::new(30.0, 20.0, 30.0) , 1.7)
-    ];
-    
-    let mut camera = Camera::new(Vec3::new(0.0, 10.0, -10.0), WIDTH, HEIGHT, FOV);
-    camera.set_rotation_x(std::f32::consts::PI * 0.5);
-    //camera.set_rotation_z(std::f32::consts::PI);
+fn run(options: Options) -> Result<(), RenderError> {
+    let SceneFile { mut scene, warnings } = scene_file::load_scene(&options.scene)?;
+    for warning in warnings {
+        eprintln!("warning: {}", warning)
+    }
+    options.apply(&mut scene);
+    if !options.quiet {
+        scene.push_observer(ConsoleProgress::new(20));
+    }
 
-    let mut scene = Scene::new(RAYS_PER_PIXEL, MAX_RECURTION, camera);
+    let start = SystemTime::now();
 
-    for sphere in spheres {
-        scene.push_object(sphere)
+    let framebuffer = scene.render()?;
+    save(&framebuffer, &options.output)?;
+    if let Some(heatmap) = &options.heatmap {
+        save(&framebuffer.sample_heatmap(), heatmap)?;
     }
 
+    if !options.quiet {
+        if let Ok(time) = start.elapsed() {
+            println!("Done in {:?}", time)
+        }
+    }
+    Ok(())
+}
+
+// A render can take hours, if the image cannot be written where asked it is
+// saved to the temporary directory, then to the current one, rather than lost
+fn save(framebuffer: &Framebuffer, path: &str) -> Result<(), RenderError> {
+    let mut error = match framebuffer.save_as_png(path) {
+        Ok(()) => return Ok(()),
+        Err(error) => error
+    };
+    for directory in [env::temp_dir(), PathBuf::from(".")] {
+        let fallback = Framebuffer::fallback_path(path, directory);
+        eprintln!("{}, saving to {} instead", error, fallback.display());
+        error = match framebuffer.save_as_png(&fallback) {
+            Ok(()) => return Ok(()),
+            Err(error) => error
+        };
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use tiny_raytracer::{Camera, Vec3, AdaptiveSampling};
+
+    #[test]
+    fn the_command_line_is_valid() {
+        Options::command().debug_assert();
+    }
 
-    scene.push_light(lights[0].clone());
-    scene.push_light(lights[1].clone());
-    scene.push_light(lights[2].clone());
-    
-
+    #[test]
+    fn spp_replaces_adaptive_sampling() {
+        let mut scene = Scene::new(1, 1, Camera::new(Vec3::new(0.0, 0.0, 0.0), 2, 2, 1.0));
+        scene.set_adaptive_sampling(Some(AdaptiveSampling::new(4, 64, 0.01)));
+        Options::parse_from(["tiny-raytracer", "--spp", "8"]).apply(&mut scene);
 
-    let start = SystemTime::now();
-    
-    scene.render("output.png");
+        let framebuffer = scene.render().unwrap();
+        assert_eq!(framebuffer.sample_count(1, 1), 8);
+    }
 
-    if let Ok(time) = start.elapsed() {
-        println!("Done in {:?}", time)
-    } 
-}
\ No newline at end of file
+    #[test]
+    fn zero_counts_are_rejected() {
+        for option in ["--width", "--height", "--spp", "-j"] {
+            assert!(Options::try_parse_from(["tiny-raytracer", option, "0"]).is_err(), "{} 0 was accepted", option);
+            assert!(Options::try_parse_from(["tiny-raytracer", option, "1"]).is_ok());
+        }
+    }
+}
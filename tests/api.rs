@@ -0,0 +1,19 @@
+use tiny_raytracer::{Scene, Camera, Vec3, Material, PointLight, Sphere, CheckerBoard};
+
+#[test]
+fn scenes_can_be_built_and_rendered_from_another_crate() {
+    let red = Material::new(Vec3::new(0.8, 0.1, 0.1), 0.9, 10.0, 0.1, 0.0, 1.0, 0.0);
+    let white = Material::new(Vec3::new(0.8, 0.8, 0.8), 0.9, 10.0, 0.1, 0.0, 1.0, 0.0);
+
+    let mut scene = Scene::new(1, 2, Camera::new(Vec3::new(0.0, 0.0, 0.0), 16, 8, std::f32::consts::PI / 3.0));
+    scene.set_threads(2);
+    scene.push_object(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, red));
+    scene.push_object(CheckerBoard::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, white.clone(), white));
+    scene.push_light(PointLight::new(Vec3::new(5.0, 5.0, 0.0), 350.0));
+
+    let framebuffer = scene.render().unwrap();
+    assert_eq!((framebuffer.width, framebuffer.height), (16, 8));
+    // The sphere is in the middle of the image
+    let center = framebuffer.get(8, 4);
+    assert!(center.x > center.y && center.x > center.z);
+}